@@ -1,5 +1,22 @@
+use std::{
+    rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
 use crate::token::{LiteralType, Token};
 
+// identifies an expression node for the resolver's side table. Ids survive cloning, so a function
+// body copied into a Callable still resolves to the same scope depths
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(usize);
+
+impl ExprId {
+    pub fn fresh() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        ExprId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Expr {
     Ternary {
@@ -16,29 +33,167 @@ pub enum Expr {
         callee: Box<Expr>,
         paren: Token,
         args: Vec<Expr>,
+        // `name: value` arguments, they always come after the positional ones
+        named: Vec<(Token, Expr)>,
+    },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+    },
+    Super {
+        id: ExprId,
+        keyword: Token,
+        method: Token,
+    },
+    This {
+        id: ExprId,
+        keyword: Token,
     },
     Grouping {
         expression: Box<Expr>,
     },
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    SetIndex {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+    },
+    // alternating string segments and embedded expressions, in source order
+    Interpolation {
+        parts: Vec<Expr>,
+    },
+    Lambda {
+        keyword: Token,
+        params: Vec<Param>,
+        // shared with every Callable created from it, evaluating a lambda in a loop is cheap
+        body: Rc<Vec<Stmt>>,
+        // set by the parser, the body contains a `yield`
+        is_generator: bool,
+    },
+    List {
+        elements: Vec<Expr>,
+    },
     Literal {
         value: LiteralType,
     },
+    Map {
+        brace: Token,
+        entries: Vec<(Expr, Expr)>,
+    },
     Unary {
         op: Token,
         right: Box<Expr>,
     },
     Variable {
+        id: ExprId,
         name: Token,
     },
     Assign {
+        id: ExprId,
         name: Token,
         value: Box<Expr>,
     },
+    // `target op= value`, `++target` and `target++`. The target is a Variable, Get or Index and
+    // op is the arithmetic operator being applied, e.g. a Plus token with the lexeme "+="
+    CompoundAssign {
+        target: Box<Expr>,
+        op: Token,
+        value: Box<Expr>,
+        // the postfix forms produce the value from before the update
+        postfix: bool,
+    },
     Logical {
         left: Box<Expr>,
         op: Token,
         right: Box<Expr>,
     },
+    // `[a, b] = [b, a]`. The target is an Expr::List of Variable, Get, Index and nested List
+    // targets, the whole value is evaluated before anything is assigned
+    AssignList {
+        target: Box<Expr>,
+        equals: Token,
+        value: Box<Expr>,
+    },
+    // the expression form, every arm is an expression and the match produces its value
+    Match {
+        keyword: Token,
+        subject: Box<Expr>,
+        arms: Vec<MatchArm<Expr>>,
+    },
+    // the `a` in `a?.b`, when it's nil the rest of the chain is skipped
+    NilGuard {
+        expression: Box<Expr>,
+    },
+    // a whole `a?.b.c()` chain, it's nil when one of the guards in it was
+    OptionalChain {
+        expression: Box<Expr>,
+    },
+}
+
+// only the trailing parameters can have a default and only the last one can be a rest
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: Token,
+    // evaluated on every call that leaves the parameter out
+    pub default: Option<Expr>,
+    // `...name` collects the remaining arguments into a list
+    pub rest: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm<T> {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: T,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    // compared with interpreter::is_equal
+    Literal(LiteralType),
+    // `_`, matches anything without binding it
+    Wildcard,
+    Binding(Token),
+    // `a | b`, alternatives never bind names
+    Or(Vec<Pattern>),
+    // `[a, b, ...rest]`, without a rest the lengths have to be equal
+    List {
+        elements: Vec<Pattern>,
+        rest: Option<Token>,
+    },
+    // `{"key": pattern, name}`, the map can have more keys than the pattern
+    Map {
+        entries: Vec<(LiteralType, Pattern)>,
+    },
+}
+
+impl Pattern {
+    pub fn bindings(&self) -> Vec<&Token> {
+        match self {
+            Pattern::Literal(_) | Pattern::Wildcard => Vec::new(),
+            Pattern::Binding(name) => vec![name],
+            Pattern::Or(alternatives) => alternatives.iter().flat_map(Pattern::bindings).collect(),
+            Pattern::List { elements, rest } => elements
+                .iter()
+                .flat_map(Pattern::bindings)
+                .chain(rest.iter().filter(|rest| rest.lexeme != "_"))
+                .collect(),
+            Pattern::Map { entries } => entries
+                .iter()
+                .flat_map(|(_, pattern)| pattern.bindings())
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -47,19 +202,56 @@ pub enum Stmt {
         statements: Vec<Stmt>,
     },
     Break,
+    // methods are always Stmt::Function
+    Class {
+        name: Token,
+        // always an Expr::Variable
+        superclass: Option<Expr>,
+        methods: Vec<Stmt>,
+    },
+    // always has an initializer and can't be assigned to afterwards
+    Const {
+        name: Token,
+        initializer: Expr,
+    },
+    Continue,
     Expression {
         expression: Expr,
     },
+    // `for (name in iterable) body`, every iteration gets a new scope with the name in it
+    ForIn {
+        name: Token,
+        keyword: Token,
+        iterable: Expr,
+        body: Box<Stmt>,
+    },
     Function {
         name: Token,
-        params: Vec<Token>,
+        params: Vec<Param>,
         body: Vec<Stmt>,
+        // set by the parser, the body contains a `yield`
+        is_generator: bool,
     },
     If {
         condition: Expr,
         then_branch: Box<Stmt>,
         else_branch: Option<Box<Stmt>>,
     },
+    Import {
+        keyword: Token,
+        // the string between the quotes, relative to the importing file
+        path: String,
+        // `import "mod";` binds the whole module to its file name
+        namespace: Option<Token>,
+        // `import x, y from "mod";` binds single names instead
+        names: Vec<Token>,
+    },
+    // the statement form, arms are statements and nothing is produced
+    Match {
+        keyword: Token,
+        subject: Expr,
+        arms: Vec<MatchArm<Stmt>>,
+    },
     Print {
         expression: Expr,
     },
@@ -67,12 +259,69 @@ pub enum Stmt {
         keyword: Token,
         value: Option<Expr>,
     },
+    Throw {
+        keyword: Token,
+        value: Expr,
+    },
+    // at least one of catch and finally is always set
+    Try {
+        body: Vec<Stmt>,
+        // the name the caught value is bound to and the catch body
+        catch: Option<(Token, Vec<Stmt>)>,
+        finally: Option<Vec<Stmt>>,
+    },
     Var {
         name: Token,
         initializer: Option<Expr>,
     },
+    // `var [a, ...rest] = xs;` and `var {name} = person;`, the pattern is a list or a map one
+    VarPattern {
+        pattern: Pattern,
+        equals: Token,
+        initializer: Expr,
+    },
     While {
         condition: Expr,
         body: Box<Stmt>,
+        // only set for desugared `for` loops, runs after the body even on `continue`
+        increment: Option<Expr>,
+    },
+    // `yield;` produces nil
+    Yield {
+        keyword: Token,
+        value: Option<Expr>,
     },
 }
+
+impl Stmt {
+    // a function is a generator when its body yields. Nested functions and lambdas don't count,
+    // they are generators of their own
+    pub fn contains_yield(&self) -> bool {
+        match self {
+            Stmt::Yield { .. } => true,
+            Stmt::Block { statements } => statements.iter().any(Stmt::contains_yield),
+            Stmt::If {
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                then_branch.contains_yield()
+                    || else_branch
+                        .as_ref()
+                        .is_some_and(|branch| branch.contains_yield())
+            }
+            Stmt::While { body, .. } | Stmt::ForIn { body, .. } => body.contains_yield(),
+            Stmt::Match { arms, .. } => arms.iter().any(|arm| arm.body.contains_yield()),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => body
+                .iter()
+                .chain(catch.iter().flat_map(|(_, body)| body))
+                .chain(finally.iter().flatten())
+                .any(Stmt::contains_yield),
+            _ => false,
+        }
+    }
+}
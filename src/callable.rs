@@ -1,14 +1,18 @@
 use std::{cell::RefCell, fmt::Display, rc::Rc};
 
 use crate::{
-    ast::Stmt,
+    ast::{Param, Stmt},
+    class::Instance,
     environment::Environment,
-    interpreter::{execute_block, InterpreterEnvironment, InterpreterSignal},
-    token::{LiteralType, Token},
+    generator::Generator,
+    interpreter::{
+        InterpreterEnvironment, InterpreterSignal, RuntimeError, evaluate, execute_block,
+    },
+    token::{LiteralType, Token, TokenType},
 };
 
 pub trait CallableTrait {
-    fn arity(&self) -> u8;
+    fn arity(&self) -> Arity;
     fn call(
         &self,
         args: &[LiteralType],
@@ -16,22 +20,71 @@ pub trait CallableTrait {
     ) -> Result<LiteralType, InterpreterSignal>;
 }
 
+// how many arguments a call can take. Defaults lower the minimum and a rest parameter removes
+// the maximum
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arity {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub fn exact(count: usize) -> Self {
+        Self {
+            min: count,
+            max: Some(count),
+        }
+    }
+
+    pub fn of(params: &[Param]) -> Self {
+        let required = params
+            .iter()
+            .filter(|param| param.default.is_none() && !param.rest)
+            .count();
+        let has_rest = params.iter().any(|param| param.rest);
+
+        Self {
+            min: required,
+            max: (!has_rest).then_some(params.len()),
+        }
+    }
+
+    pub fn accepts(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{max}"),
+            Some(max) => write!(f, "{} to {max}", self.min),
+            None => write!(f, "at least {}", self.min),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Callable {
     Function {
-        name: Box<Token>,
-        params: Vec<Token>,
-        body: Vec<Stmt>,
+        // lambdas don't have a name
+        name: Option<Box<Token>>,
+        params: Vec<Param>,
+        // shared, bcs methods are cloned every time they are bound to an instance
+        body: Rc<Vec<Stmt>>,
         closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+        // the body yields, so calls return a generator instead of running it
+        is_generator: bool,
     },
     NativeFunction(NativeFunction),
 }
 
 impl CallableTrait for Callable {
-    fn arity(&self) -> u8 {
+    fn arity(&self) -> Arity {
         match self {
-            Callable::Function { params, .. } => params.len() as u8,
-            Callable::NativeFunction(native_function) => native_function.arity,
+            Callable::Function { params, .. } => Arity::of(params),
+            Callable::NativeFunction(native_function) => Arity::exact(native_function.arity()),
         }
     }
 
@@ -40,41 +93,210 @@ impl CallableTrait for Callable {
         args: &[LiteralType],
         env: &InterpreterEnvironment,
     ) -> Result<LiteralType, InterpreterSignal> {
+        match self {
+            Callable::Function { params, .. } => {
+                let positional = positional_count(params);
+                let mut slots: Vec<Option<LiteralType>> = args.iter().cloned().map(Some).collect();
+                let rest = if slots.len() > positional {
+                    slots.split_off(positional).into_iter().flatten().collect()
+                } else {
+                    Vec::new()
+                };
+                slots.resize(positional, None);
+
+                self.invoke(slots, rest, env)
+            }
+            Callable::NativeFunction(native_function) => native_function.call(args),
+        }
+    }
+}
+
+impl Callable {
+    // `f(1, b: 2)`. Named arguments fill the parameters with the same name, after the positional
+    // ones took theirs. Only functions declared in the script have parameter names
+    pub fn call_with_named(
+        &self,
+        args: &[LiteralType],
+        named: &[(Token, LiteralType)],
+        paren: &Token,
+        env: &InterpreterEnvironment,
+    ) -> Result<LiteralType, InterpreterSignal> {
+        let Callable::Function { params, .. } = self else {
+            Err(RuntimeError::new(
+                paren,
+                format!("Native function {self} doesn't take named arguments."),
+            ))?
+        };
+
+        let positional = positional_count(params);
+        let has_rest = params.len() > positional;
+        if args.len() > positional && !has_rest {
+            Err(RuntimeError::new(
+                paren,
+                format!(
+                    "Expected {} arguments but got {}.",
+                    self.arity(),
+                    args.len() + named.len()
+                ),
+            ))?
+        }
+
+        let mut slots: Vec<Option<LiteralType>> = vec![None; positional];
+        for (slot, arg) in slots.iter_mut().zip(args) {
+            *slot = Some(arg.clone());
+        }
+        let rest = args.get(positional..).unwrap_or_default().to_vec();
+
+        for (name, value) in named {
+            let Some(i) = params[..positional]
+                .iter()
+                .position(|param| param.name.lexeme == name.lexeme)
+            else {
+                Err(RuntimeError::new(
+                    paren,
+                    format!("Unknown parameter {}.", name.lexeme),
+                ))?
+            };
+
+            if slots[i].is_some() {
+                Err(RuntimeError::new(
+                    paren,
+                    format!("Parameter {} got more than one argument.", name.lexeme),
+                ))?
+            }
+            slots[i] = Some(value.clone());
+        }
+
+        let missing = params
+            .iter()
+            .zip(&slots)
+            .find(|(param, slot)| slot.is_none() && param.default.is_none());
+        if let Some((param, _)) = missing {
+            Err(RuntimeError::new(
+                paren,
+                format!("Missing argument for parameter {}.", param.name.lexeme),
+            ))?
+        }
+
+        self.invoke(slots, rest, env)
+    }
+
+    // runs a script function. There is a slot for every parameter except the rest one, empty
+    // slots get the parameter's default
+    fn invoke(
+        &self,
+        slots: Vec<Option<LiteralType>>,
+        rest: Vec<LiteralType>,
+        env: &InterpreterEnvironment,
+    ) -> Result<LiteralType, InterpreterSignal> {
+        let Callable::Function {
+            params,
+            body,
+            closure,
+            is_initializer,
+            is_generator,
+            ..
+        } = self
+        else {
+            unreachable!("Shouldn't happen. Only script functions are invoked")
+        };
+
+        // the globals are the ones of the file the function was declared in, which aren't
+        // the caller's when it was imported from another module
+        let environment = InterpreterEnvironment {
+            globals: Environment::root(closure),
+            environment: Rc::new(RefCell::new(Environment::with_enclosing(closure))),
+            locals: Rc::clone(&env.locals),
+            modules: Rc::clone(&env.modules),
+        };
+
+        // defined one by one, so a default can use the parameters before it
+        let mut slots = slots.into_iter();
+        let mut rest = Some(rest);
+        for param in params {
+            let value = if param.rest {
+                let rest = rest.take().unwrap_or_default();
+                LiteralType::List(Rc::new(RefCell::new(rest)))
+            } else {
+                match (slots.next().flatten(), &param.default) {
+                    (Some(arg), _) => arg,
+                    (None, Some(default)) => evaluate(default, &environment)?,
+                    (None, None) => unreachable!("Shouldn't happen. The arity is checked"),
+                }
+            };
+            environment
+                .environment
+                .borrow_mut()
+                .define(&param.name.lexeme, Some(value));
+        }
+
+        // the body only starts running once the generator is asked for a value
+        if *is_generator {
+            let generator = Generator::new(self.to_string(), Rc::clone(body), environment);
+            return Ok(LiteralType::Generator(Rc::new(RefCell::new(generator))));
+        }
+
+        let result = match execute_block(body, &environment) {
+            Err(InterpreterSignal::Return(v)) => Ok(v),
+            v => v.map(|_| LiteralType::Nil),
+        };
+
+        // an initializer always returns the instance, even on an early `return;`
+        if *is_initializer {
+            return result.map(|_| this_of(closure));
+        }
+
+        result
+    }
+
+    // creates a copy of the method whose closure has `this` bound to the instance
+    pub fn bind(&self, instance: &Rc<RefCell<Instance>>) -> Callable {
         match self {
             Callable::Function {
-                name: _,
+                name,
                 params,
                 body,
                 closure,
+                is_initializer,
+                is_generator,
             } => {
-                let environment = Rc::new(RefCell::new(Environment::with_enclosing(closure)));
-
-                for (param, arg) in params.iter().zip(args) {
-                    environment
-                        .borrow_mut()
-                        .define(&param.lexeme, Some(arg.clone()));
-                }
-
-                let environment = InterpreterEnvironment {
-                    globals: Rc::clone(&env.globals),
-                    environment,
-                };
+                let mut environment = Environment::with_enclosing(closure);
+                environment.define("this", Some(LiteralType::Instance(Rc::clone(instance))));
 
-                match execute_block(body, &environment) {
-                    Err(InterpreterSignal::Return(v)) => Ok(v),
-                    v => v.map(|_| LiteralType::Nil),
+                Callable::Function {
+                    name: name.clone(),
+                    params: params.clone(),
+                    body: Rc::clone(body),
+                    closure: Rc::new(RefCell::new(environment)),
+                    is_initializer: *is_initializer,
+                    is_generator: *is_generator,
                 }
             }
-            Callable::NativeFunction(native_function) => (native_function.call_impl)(args),
+            Callable::NativeFunction(_) => self.clone(),
         }
     }
 }
 
+// every parameter but the rest one
+fn positional_count(params: &[Param]) -> usize {
+    params.iter().filter(|param| !param.rest).count()
+}
+
+fn this_of(closure: &Rc<RefCell<Environment>>) -> LiteralType {
+    closure
+        .borrow()
+        .get_at(0, &Token::new(TokenType::This, "this", None, 0))
+        .flatten()
+        .expect("Initializer closure should always have `this` bound")
+}
+
 #[derive(Debug, Clone)]
 pub struct NativeFunction {
     name: String,
     arity: u8,
     call_impl: fn(&[LiteralType]) -> Result<LiteralType, InterpreterSignal>,
+    // set for methods of built-in values, it's passed in front of the arguments
+    receiver: Option<Box<LiteralType>>,
 }
 
 impl NativeFunction {
@@ -87,6 +309,39 @@ impl NativeFunction {
             name,
             arity,
             call_impl,
+            receiver: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // a copy with the first argument already given, like Callable::bind does with `this`
+    pub fn bind(&self, receiver: LiteralType) -> NativeFunction {
+        NativeFunction {
+            receiver: Some(Box::new(receiver)),
+            ..self.clone()
+        }
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    fn arity(&self) -> usize {
+        self.arity as usize - usize::from(self.receiver.is_some())
+    }
+
+    fn call(&self, args: &[LiteralType]) -> Result<LiteralType, InterpreterSignal> {
+        match &self.receiver {
+            Some(receiver) => {
+                let args: Vec<LiteralType> = std::iter::once(receiver.as_ref().clone())
+                    .chain(args.iter().cloned())
+                    .collect();
+                (self.call_impl)(&args)
+            }
+            None => (self.call_impl)(args),
         }
     }
 }
@@ -94,9 +349,14 @@ impl NativeFunction {
 impl Display for Callable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Callable::Function { name, .. } => {
+            Callable::Function {
+                name: Some(name), ..
+            } => {
                 write!(f, "{}", name.lexeme)
             }
+            Callable::Function { name: None, .. } => {
+                write!(f, "lambda")
+            }
             Callable::NativeFunction(native_function) => {
                 write!(f, "{}", native_function.name)
             }
@@ -0,0 +1,141 @@
+use std::{cell::RefCell, collections::HashMap, fmt::Display, rc::Rc};
+
+use crate::{
+    callable::{Arity, Callable, CallableTrait},
+    interpreter::{InterpreterEnvironment, InterpreterSignal, RuntimeError},
+    token::{LiteralType, Token},
+};
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    superclass: Option<Rc<Class>>,
+    methods: HashMap<String, Callable>,
+}
+
+impl Class {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<Class>>,
+        methods: HashMap<String, Callable>,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    // like calling the class, with the named arguments going to `init`
+    pub fn call_with_named(
+        self: &Rc<Self>,
+        args: &[LiteralType],
+        named: &[(Token, LiteralType)],
+        paren: &Token,
+        env: &InterpreterEnvironment,
+    ) -> Result<LiteralType, InterpreterSignal> {
+        let Some(initializer) = self.find_method("init") else {
+            // without an initializer there are no parameters to name
+            let (name, _) = &named[0];
+            Err(RuntimeError::new(
+                paren,
+                format!("Unknown parameter {}.", name.lexeme),
+            ))?
+        };
+
+        let instance = Rc::new(RefCell::new(Instance::new(Rc::clone(self))));
+        initializer
+            .bind(&instance)
+            .call_with_named(args, named, paren, env)?;
+
+        Ok(LiteralType::Instance(instance))
+    }
+
+    // walks up the superclass chain, so subclasses override inherited methods
+    pub fn find_method(&self, name: &str) -> Option<&Callable> {
+        self.methods.get(name).or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name))
+        })
+    }
+}
+
+// implemented on the Rc, bcs every instance created by a call keeps a reference to its class
+impl CallableTrait for Rc<Class> {
+    fn arity(&self) -> Arity {
+        self.find_method("init")
+            .map_or(Arity::exact(0), |init| init.arity())
+    }
+
+    fn call(
+        &self,
+        args: &[LiteralType],
+        env: &InterpreterEnvironment,
+    ) -> Result<LiteralType, InterpreterSignal> {
+        let instance = Rc::new(RefCell::new(Instance::new(Rc::clone(self))));
+
+        if let Some(initializer) = self.find_method("init") {
+            initializer.bind(&instance).call(args, env)?;
+        }
+
+        Ok(LiteralType::Instance(instance))
+    }
+}
+
+impl Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    class: Rc<Class>,
+    fields: HashMap<String, LiteralType>,
+}
+
+impl Instance {
+    pub fn new(class: Rc<Class>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    // fields shadow methods. Methods are bound to the instance on every access, so they can be
+    // stored in variables and called later
+    pub fn get(
+        instance: &Rc<RefCell<Instance>>,
+        name: &Token,
+    ) -> Result<LiteralType, RuntimeError> {
+        let this = instance.borrow();
+        if let Some(field) = this.fields.get(&name.lexeme) {
+            return Ok(field.clone());
+        }
+
+        if let Some(method) = this.class.find_method(&name.lexeme) {
+            return Ok(LiteralType::Callable(method.bind(instance)));
+        }
+
+        Err(RuntimeError::new(
+            name,
+            format!("Undefined property {}.", name.lexeme),
+        ))
+    }
+
+    // only the fields, methods aren't looked up
+    pub fn field(&self, name: &str) -> Option<LiteralType> {
+        self.fields.get(name).cloned()
+    }
+
+    pub fn set(&mut self, name: &Token, value: LiteralType) {
+        self.fields.insert(name.lexeme.clone(), value);
+    }
+}
+
+impl Display for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
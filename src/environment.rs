@@ -1,21 +1,29 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 
 use crate::token::{LiteralType, Token};
 
 #[derive(Debug, Clone)]
 pub struct Environment {
     values: HashMap<String, Option<LiteralType>>,
+    // names declared with `const`, they can't be assigned to after the declaration
+    constants: HashSet<String>,
     enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
 pub enum EnvironmentError {
     AssignError,
+    ConstantAssignError,
 }
 
 impl Environment {
     pub fn new() -> Self {
         Self {
             values: HashMap::new(),
+            constants: HashSet::new(),
             enclosing: None,
         }
     }
@@ -23,6 +31,7 @@ impl Environment {
     pub fn with_enclosing(enclosing: &Rc<RefCell<Environment>>) -> Self {
         Self {
             values: HashMap::new(),
+            constants: HashSet::new(),
             enclosing: Some(Rc::clone(enclosing)),
         }
     }
@@ -31,9 +40,20 @@ impl Environment {
         // do not like this at all. String is allocated each time a variable is defined. Might be
         // bad or might be good. I don't know :D
         self.values.insert(name.to_string(), val);
+        // redeclaring a constant with `var` makes it a variable again
+        self.constants.remove(name);
+    }
+
+    pub fn define_constant(&mut self, name: &str, val: LiteralType) {
+        self.values.insert(name.to_string(), Some(val));
+        self.constants.insert(name.to_string());
     }
 
     pub fn assign(&mut self, name: &Token, val: LiteralType) -> Result<(), EnvironmentError> {
+        if self.constants.contains(&name.lexeme) {
+            return Err(EnvironmentError::ConstantAssignError);
+        }
+
         let cloned = val.clone();
         let assigned = self
             .values
@@ -63,4 +83,49 @@ impl Environment {
 
         value.cloned()
     }
+
+    // only looks at this environment, not the enclosing ones
+    pub fn get_local(&self, name: &str) -> Option<Option<LiteralType>> {
+        self.values.get(name).cloned()
+    }
+
+    // the outermost environment, which holds the globals of the file the chain was created in
+    pub fn root(environment: &Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+        match &environment.borrow().enclosing {
+            Some(enclosing) => Environment::root(enclosing),
+            None => Rc::clone(environment),
+        }
+    }
+
+    pub fn get_at(&self, distance: usize, name: &Token) -> Option<Option<LiteralType>> {
+        if distance == 0 {
+            return self.values.get(&name.lexeme).cloned();
+        }
+
+        self.enclosing.as_ref()?.borrow().get_at(distance - 1, name)
+    }
+
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        val: LiteralType,
+    ) -> Result<(), EnvironmentError> {
+        if distance == 0 {
+            if self.constants.contains(&name.lexeme) {
+                return Err(EnvironmentError::ConstantAssignError);
+            }
+
+            return self
+                .values
+                .get_mut(&name.lexeme)
+                .map(|l| *l = Some(val))
+                .ok_or(EnvironmentError::AssignError);
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(distance - 1, name, val),
+            None => Err(EnvironmentError::AssignError),
+        }
+    }
 }
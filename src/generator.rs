@@ -0,0 +1,396 @@
+use std::{cell::RefCell, fmt::Display, mem, rc::Rc};
+
+use crate::{
+    ast::{MatchArm, Stmt},
+    callable::{Callable, NativeFunction},
+    interpreter::{
+        InterpreterEnvironment, InterpreterSignal, RuntimeError, error_value, evaluate, execute,
+        is_truthy, loop_scope, new_scope, select_arm,
+    },
+    iteration::Iteration,
+    token::{LiteralType, Token},
+};
+
+// the body of a called generator function, paused at its last `yield`. The interpreter runs on
+// the rust stack, so a paused body can't just be left on it. Instead every statement between the
+// body and the `yield` leaves a cursor on the way out and picks up from it on the way back in
+#[derive(Debug)]
+pub struct Generator {
+    name: String,
+    body: Rc<Vec<Stmt>>,
+    environment: InterpreterEnvironment,
+    // the innermost statement first, so resuming pops them from the outside in
+    cursors: Vec<Cursor>,
+    running: bool,
+    done: bool,
+}
+
+#[derive(Debug)]
+enum Cursor {
+    // the `yield` itself, it's finished once the body resumes
+    Yield,
+    // the statement of the block that yielded and the block's scope
+    Block {
+        index: usize,
+        environment: InterpreterEnvironment,
+    },
+    // the branch of an if that yielded
+    Branch(bool),
+    // the body of a loop yielded, the increment and the condition come after it
+    Loop,
+    // the for-in iteration and the scope of the iteration that yielded
+    ForIn {
+        iteration: Iteration,
+        environment: InterpreterEnvironment,
+    },
+    // the chosen arm of a match, its scope is the one with the bindings
+    Arm {
+        index: usize,
+        environment: InterpreterEnvironment,
+    },
+    Try(TryPhase),
+}
+
+#[derive(Debug)]
+enum TryPhase {
+    Body,
+    // the caught value, it's only there when the catch starts. A resumed catch has it in the
+    // scope of its block already
+    Catch(Option<LiteralType>),
+    // what the try ends with once finally is done
+    Finally(Result<(), InterpreterSignal>),
+}
+
+// Some is a yielded value, None means the statement ran to its end
+type Resumed = Result<Option<LiteralType>, InterpreterSignal>;
+
+impl Generator {
+    // the environment already has the arguments bound to the parameters
+    pub fn new(name: String, body: Rc<Vec<Stmt>>, environment: InterpreterEnvironment) -> Self {
+        Self {
+            name,
+            body,
+            environment,
+            cursors: Vec::new(),
+            running: false,
+            done: false,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    // `generator.next()` and `generator.done`
+    pub fn get(
+        generator: &Rc<RefCell<Generator>>,
+        name: &Token,
+    ) -> Result<LiteralType, RuntimeError> {
+        match name.lexeme.as_str() {
+            "next" => {
+                let next = next_function().bind(LiteralType::Generator(Rc::clone(generator)));
+                Ok(LiteralType::Callable(Callable::NativeFunction(next)))
+            }
+            "done" => Ok(LiteralType::Bool(generator.borrow().done)),
+            _ => Err(RuntimeError::new(
+                name,
+                format!("Undefined property {}.", name.lexeme),
+            )),
+        }
+    }
+}
+
+impl Display for Generator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<generator {}>", self.name)
+    }
+}
+
+fn next_function() -> NativeFunction {
+    let next = |args: &[LiteralType]| match &args[0] {
+        LiteralType::Generator(generator) => resume(generator),
+        _ => unreachable!("Shouldn't happen. next is only bound to generators"),
+    };
+
+    NativeFunction::new("next".to_string(), 1, next)
+}
+
+// runs the body until the next `yield` and returns the yielded value. The value of a `return` is
+// the last one, after that it's always nil
+pub fn resume(generator: &Rc<RefCell<Generator>>) -> Result<LiteralType, InterpreterSignal> {
+    // the generator isn't borrowed while the body runs, it might print itself or check if it's
+    // done
+    let (body, environment, mut cursors) = {
+        let mut generator = generator.borrow_mut();
+        if generator.done {
+            return Ok(LiteralType::Nil);
+        }
+        if generator.running {
+            Err(InterpreterSignal::NativeRuntimeError {
+                msg: "Generator is already running.".to_string(),
+            })?
+        }
+        generator.running = true;
+
+        (
+            Rc::clone(&generator.body),
+            generator.environment.clone(),
+            mem::take(&mut generator.cursors),
+        )
+    };
+
+    let result = resume_block(&body, environment, &mut cursors);
+
+    let mut generator = generator.borrow_mut();
+    generator.running = false;
+    match result {
+        Ok(Some(value)) => {
+            generator.cursors = cursors;
+            Ok(value)
+        }
+        Ok(None) => {
+            generator.done = true;
+            Ok(LiteralType::Nil)
+        }
+        Err(InterpreterSignal::Return(value)) => {
+            generator.done = true;
+            Ok(value)
+        }
+        Err(signal) => {
+            generator.done = true;
+            Err(signal)
+        }
+    }
+}
+
+// leaves the statement's cursor behind and passes the yielded value up
+fn suspend(cursors: &mut Vec<Cursor>, cursor: Cursor, value: LiteralType) -> Resumed {
+    cursors.push(cursor);
+    Ok(Some(value))
+}
+
+// a resumed block goes on in the scope it was paused in, the given one is only used when the
+// block starts
+fn resume_block(
+    statements: &[Stmt],
+    environment: InterpreterEnvironment,
+    cursors: &mut Vec<Cursor>,
+) -> Resumed {
+    let (start, environment) = match cursors.pop() {
+        Some(Cursor::Block { index, environment }) => (index, environment),
+        _ => (0, environment),
+    };
+
+    for (index, statement) in statements.iter().enumerate().skip(start) {
+        if let Some(value) = resume_statement(statement, &environment, cursors)? {
+            return suspend(cursors, Cursor::Block { index, environment }, value);
+        }
+    }
+
+    Ok(None)
+}
+
+// the statements that can have a `yield` inside, everything else is left to the interpreter.
+// When there are cursors left, the last one belongs to this statement
+fn resume_statement(
+    statement: &Stmt,
+    environment: &InterpreterEnvironment,
+    cursors: &mut Vec<Cursor>,
+) -> Resumed {
+    match statement {
+        Stmt::Yield { value, .. } => {
+            if let Some(Cursor::Yield) = cursors.pop() {
+                return Ok(None);
+            }
+
+            let value = match value {
+                Some(value) => evaluate(value, environment)?,
+                None => LiteralType::Nil,
+            };
+            suspend(cursors, Cursor::Yield, value)
+        }
+        Stmt::Block { statements } => resume_block(statements, new_scope(environment), cursors),
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            let branch = match cursors.pop() {
+                Some(Cursor::Branch(branch)) => branch,
+                _ => is_truthy(&evaluate(condition, environment)?),
+            };
+
+            let taken = if branch {
+                Some(then_branch)
+            } else {
+                else_branch.as_ref()
+            };
+            let Some(taken) = taken else {
+                return Ok(None);
+            };
+
+            match resume_statement(taken, environment, cursors)? {
+                Some(value) => suspend(cursors, Cursor::Branch(branch), value),
+                None => Ok(None),
+            }
+        }
+        Stmt::While {
+            condition,
+            body,
+            increment,
+        } => {
+            // a resumed loop is already in its body, the condition was checked before the yield
+            let mut resumed = matches!(cursors.pop(), Some(Cursor::Loop));
+            while resumed || is_truthy(&evaluate(condition, environment)?) {
+                resumed = false;
+
+                match resume_statement(body, environment, cursors) {
+                    Ok(Some(value)) => return suspend(cursors, Cursor::Loop, value),
+                    Ok(None) | Err(InterpreterSignal::Continue) => (),
+                    Err(InterpreterSignal::Break) => break,
+                    Err(signal) => return Err(signal),
+                }
+
+                if let Some(increment) = increment {
+                    evaluate(increment, environment)?;
+                }
+            }
+
+            Ok(None)
+        }
+        Stmt::ForIn {
+            name,
+            keyword,
+            iterable,
+            body,
+        } => {
+            let (mut iteration, mut resumed) = match cursors.pop() {
+                Some(Cursor::ForIn {
+                    iteration,
+                    environment,
+                }) => (iteration, Some(environment)),
+                _ => {
+                    let iterable = evaluate(iterable, environment)?;
+                    (Iteration::new(iterable, keyword, environment)?, None)
+                }
+            };
+
+            loop {
+                let loop_environment = match resumed.take() {
+                    Some(loop_environment) => loop_environment,
+                    None => match iteration.next(keyword, environment)? {
+                        Some(value) => loop_scope(name, value, environment),
+                        None => break,
+                    },
+                };
+
+                match resume_statement(body, &loop_environment, cursors) {
+                    Ok(Some(value)) => {
+                        let cursor = Cursor::ForIn {
+                            iteration,
+                            environment: loop_environment,
+                        };
+                        return suspend(cursors, cursor, value);
+                    }
+                    Ok(None) | Err(InterpreterSignal::Continue) => (),
+                    Err(InterpreterSignal::Break) => break,
+                    Err(signal) => return Err(signal),
+                }
+            }
+
+            Ok(None)
+        }
+        Stmt::Match {
+            keyword,
+            subject,
+            arms,
+        } => {
+            let (index, arm_environment) = match cursors.pop() {
+                Some(Cursor::Arm { index, environment }) => (index, environment),
+                _ => {
+                    let subject = evaluate(subject, environment)?;
+                    select_arm(keyword, &subject, arms, environment)?
+                }
+            };
+
+            let MatchArm { body, .. } = &arms[index];
+            match resume_statement(body, &arm_environment, cursors)? {
+                Some(value) => suspend(
+                    cursors,
+                    Cursor::Arm {
+                        index,
+                        environment: arm_environment,
+                    },
+                    value,
+                ),
+                None => Ok(None),
+            }
+        }
+        Stmt::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            let mut phase = match cursors.pop() {
+                Some(Cursor::Try(phase)) => phase,
+                _ => TryPhase::Body,
+            };
+
+            // same rules as the interpreter's try, just split up into the parts a yield can
+            // pause
+            loop {
+                phase = match phase {
+                    TryPhase::Body => match resume_block(body, new_scope(environment), cursors) {
+                        Ok(Some(value)) => {
+                            return suspend(cursors, Cursor::Try(TryPhase::Body), value);
+                        }
+                        Ok(None) => TryPhase::Finally(Ok(())),
+                        Err(InterpreterSignal::Throw { value, .. }) if catch.is_some() => {
+                            TryPhase::Catch(Some(value))
+                        }
+                        Err(InterpreterSignal::RuntimeError(err)) if catch.is_some() => {
+                            TryPhase::Catch(Some(error_value(err)))
+                        }
+                        Err(signal) => TryPhase::Finally(Err(signal)),
+                    },
+                    TryPhase::Catch(caught) => {
+                        let Some((name, catch_body)) = catch else {
+                            unreachable!("Shouldn't happen. Only a try with a catch catches")
+                        };
+
+                        let catch_environment = new_scope(environment);
+                        if let Some(caught) = caught {
+                            catch_environment
+                                .environment
+                                .borrow_mut()
+                                .define(&name.lexeme, Some(caught));
+                        }
+
+                        match resume_block(catch_body, catch_environment, cursors) {
+                            Ok(Some(value)) => {
+                                return suspend(cursors, Cursor::Try(TryPhase::Catch(None)), value);
+                            }
+                            Ok(None) => TryPhase::Finally(Ok(())),
+                            Err(signal) => TryPhase::Finally(Err(signal)),
+                        }
+                    }
+                    TryPhase::Finally(outcome) => {
+                        let Some(finally) = finally else {
+                            return outcome.map(|()| None);
+                        };
+
+                        return match resume_block(finally, new_scope(environment), cursors) {
+                            Ok(Some(value)) => {
+                                suspend(cursors, Cursor::Try(TryPhase::Finally(outcome)), value)
+                            }
+                            // a signal coming out of finally replaces the pending one
+                            Ok(None) => outcome.map(|()| None),
+                            Err(signal) => Err(signal),
+                        };
+                    }
+                };
+            }
+        }
+        _ => execute(statement, environment).map(|()| None),
+    }
+}
@@ -1,15 +1,26 @@
-use core::panic;
 use std::{
     cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    fs,
     rc::Rc,
     time::{SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
-    ast::{Expr, Stmt},
+    RunError,
+    ast::{Expr, ExprId, MatchArm, Pattern, Stmt},
     callable::{Callable, CallableTrait, NativeFunction},
-    environment::Environment,
+    class::{Class, Instance},
+    environment::{Environment, EnvironmentError},
+    generator::Generator,
+    iteration::Iteration,
+    map::{Map, MapKey},
+    module::{self, Module, Modules},
+    range::Range,
+    resolver::Locals,
     token::{LiteralType, Token, TokenType},
+    utils::StringUtils,
 };
 
 type InterpreterResult = Result<LiteralType, InterpreterSignal>;
@@ -20,9 +31,12 @@ pub struct RuntimeError {
     pub message: String,
 }
 
+#[derive(Debug, Clone)]
 pub struct InterpreterEnvironment {
     pub globals: Rc<RefCell<Environment>>,
     pub environment: Rc<RefCell<Environment>>,
+    pub locals: Rc<RefCell<Locals>>,
+    pub modules: Rc<RefCell<Modules>>,
 }
 
 impl RuntimeError {
@@ -34,11 +48,17 @@ impl RuntimeError {
     }
 }
 
+#[derive(Debug)]
 pub enum InterpreterSignal {
     RuntimeError(RuntimeError),
     NativeRuntimeError { msg: String },
     Break,
+    Continue,
     Return(LiteralType),
+    // a nil guard found nil, the optional chain around it turns this into nil
+    ShortCircuit,
+    // a value thrown with `throw`, the token is the keyword, so an uncaught throw has a line
+    Throw { value: LiteralType, token: Token },
 }
 /*
     This two impl blocks are for the ? operator. I'm too lazy to write the wrapping code for the enums and it also looks ugly,
@@ -57,8 +77,15 @@ impl From<InterpreterSignal> for RuntimeError {
         match value {
             InterpreterSignal::RuntimeError(runtime_error) => runtime_error,
             InterpreterSignal::Break => panic!("Not a runtime error"),
+            InterpreterSignal::Continue => panic!("Not a runtime error"),
             InterpreterSignal::Return(_) => panic!("Not a runtime error"),
+            InterpreterSignal::ShortCircuit => panic!("Not a runtime error"),
             InterpreterSignal::NativeRuntimeError { .. } => panic!("Not a runtime error"),
+            // nobody caught it, so it ends the script like any other runtime error
+            InterpreterSignal::Throw { value, token } => RuntimeError {
+                token,
+                message: format!("Uncaught exception: {value}"),
+            },
         }
     }
 }
@@ -66,6 +93,8 @@ impl From<InterpreterSignal> for RuntimeError {
 pub fn interpret(
     statements: &Vec<Stmt>,
     environment: &Rc<RefCell<Environment>>,
+    locals: &Rc<RefCell<Locals>>,
+    modules: &Rc<RefCell<Modules>>,
 ) -> Result<(), InterpreterSignal> {
     let clock = |_arg: &[LiteralType]| {
         Ok(LiteralType::Number(
@@ -81,19 +110,26 @@ pub fn interpret(
     let environment = InterpreterEnvironment {
         globals: Rc::clone(environment),
         environment: Rc::clone(environment),
+        locals: Rc::clone(locals),
+        modules: Rc::clone(modules),
     };
-    environment.globals.borrow_mut().define(
-        "clock",
-        Some(LiteralType::Callable(Callable::NativeFunction(
-            clock_function,
-        ))),
-    );
-    environment.globals.borrow_mut().define(
-        "read_input",
-        Some(LiteralType::Callable(Callable::NativeFunction(
-            read_input_function(),
-        ))),
-    );
+    let natives = [
+        clock_function,
+        read_input_function(),
+        len_function(),
+        push_function(),
+        keys_function(),
+        values_function(),
+        has_function(),
+        remove_function(),
+    ];
+    for native in natives {
+        let name = native.name().to_string();
+        environment.globals.borrow_mut().define(
+            &name,
+            Some(LiteralType::Callable(Callable::NativeFunction(native))),
+        );
+    }
     for statement in statements {
         execute(statement, &environment)?
     }
@@ -101,7 +137,7 @@ pub fn interpret(
     Ok(())
 }
 
-fn execute(
+pub fn execute(
     statement: &Stmt,
     environment: &InterpreterEnvironment,
 ) -> Result<(), InterpreterSignal> {
@@ -122,8 +158,28 @@ fn execute(
             };
             curr_environment.borrow_mut().define(&name.lexeme, value);
         }
+        Stmt::Const { name, initializer } => {
+            let value = evaluate(initializer, environment)?;
+            curr_environment
+                .borrow_mut()
+                .define_constant(&name.lexeme, value);
+        }
+        Stmt::VarPattern {
+            pattern,
+            equals,
+            initializer,
+        } => {
+            let value = evaluate(initializer, environment)?;
+            let mut bindings = Vec::new();
+            match_pattern(pattern, &value, &mut bindings)
+                .map_err(|msg| RuntimeError::new(equals, format!("Can't destructure: {msg}")))?;
+
+            for (name, value) in bindings {
+                curr_environment.borrow_mut().define(&name, Some(value));
+            }
+        }
         Stmt::Block { statements } => {
-            execute_block(statements, environment)?;
+            execute_block(statements, &new_scope(environment))?;
         }
         Stmt::If {
             condition,
@@ -136,24 +192,116 @@ fn execute(
                 execute(else_branch, environment)?;
             }
         }
-        Stmt::While { condition, body } => {
+        Stmt::While {
+            condition,
+            body,
+            increment,
+        } => {
             while is_truthy(&evaluate(condition, environment)?) {
-                let result = execute(body, environment);
-                if result.is_err() {
-                    break;
+                // loops only consume their own signals, everything else goes up to the caller
+                match execute(body, environment) {
+                    Ok(()) | Err(InterpreterSignal::Continue) => (),
+                    Err(InterpreterSignal::Break) => break,
+                    Err(signal) => return Err(signal),
+                }
+
+                if let Some(increment) = increment {
+                    evaluate(increment, environment)?;
+                }
+            }
+        }
+        Stmt::ForIn {
+            name,
+            keyword,
+            iterable,
+            body,
+        } => {
+            let iterable = evaluate(iterable, environment)?;
+            let mut iteration = Iteration::new(iterable, keyword, environment)?;
+
+            while let Some(value) = iteration.next(keyword, environment)? {
+                match execute(body, &loop_scope(name, value, environment)) {
+                    Ok(()) | Err(InterpreterSignal::Continue) => (),
+                    Err(InterpreterSignal::Break) => break,
+                    Err(signal) => return Err(signal),
                 }
             }
         }
         Stmt::Break => Err(InterpreterSignal::Break)?,
-        Stmt::Function { name, params, body } => {
+        Stmt::Continue => Err(InterpreterSignal::Continue)?,
+        Stmt::Class {
+            name,
+            superclass,
+            methods,
+        } => {
+            let superclass = match superclass {
+                Some(superclass) => match evaluate(superclass, environment)? {
+                    LiteralType::Class(class) => Some(class),
+                    _ => {
+                        let token = match superclass {
+                            Expr::Variable { name, .. } => name,
+                            _ => name,
+                        };
+                        Err(RuntimeError::new(
+                            token,
+                            "Superclass must be a class.".to_string(),
+                        ))?
+                    }
+                },
+                None => None,
+            };
+
+            // methods of a subclass close over an extra environment that holds `super`
+            let method_environment = match &superclass {
+                Some(superclass) => {
+                    let mut environment = Environment::with_enclosing(curr_environment);
+                    environment.define("super", Some(LiteralType::Class(Rc::clone(superclass))));
+                    Rc::new(RefCell::new(environment))
+                }
+                None => Rc::clone(curr_environment),
+            };
+
+            let mut class_methods = HashMap::new();
+            for method in methods {
+                if let Stmt::Function {
+                    name,
+                    params,
+                    body,
+                    is_generator,
+                } = method
+                {
+                    let function = Callable::Function {
+                        name: Some(Box::new(name.clone())),
+                        body: Rc::new(body.to_vec()),
+                        params: params.to_vec(),
+                        closure: Rc::clone(&method_environment),
+                        is_initializer: name.lexeme == "init",
+                        is_generator: *is_generator,
+                    };
+                    class_methods.insert(name.lexeme.clone(), function);
+                }
+            }
+
+            let class = Class::new(name.lexeme.clone(), superclass, class_methods);
+            curr_environment
+                .borrow_mut()
+                .define(&name.lexeme, Some(LiteralType::Class(Rc::new(class))));
+        }
+        Stmt::Function {
+            name,
+            params,
+            body,
+            is_generator,
+        } => {
             let function = Callable::Function {
-                name: Box::new(name.clone()),
-                body: body.to_vec(),
+                name: Some(Box::new(name.clone())),
+                body: Rc::new(body.to_vec()),
                 params: params.to_vec(),
                 closure: Rc::clone(curr_environment),
+                is_initializer: false,
+                is_generator: *is_generator,
             };
-            environment
-                .globals
+            curr_environment
                 .borrow_mut()
                 .define(&name.lexeme, Some(LiteralType::Callable(function)));
         }
@@ -166,32 +314,335 @@ fn execute(
 
             return Err(InterpreterSignal::Return(value));
         }
+        Stmt::Import {
+            keyword,
+            path,
+            namespace,
+            names,
+        } => {
+            let module = import_module(keyword, path, environment)?;
+
+            if let Some(namespace) = namespace {
+                curr_environment
+                    .borrow_mut()
+                    .define(&namespace.lexeme, Some(LiteralType::Module(module)));
+                return Ok(());
+            }
+
+            for name in names {
+                let value = module.get(&name.lexeme).ok_or_else(|| {
+                    RuntimeError::new(
+                        name,
+                        format!("Module {} has no binding {}.", module.name, name.lexeme),
+                    )
+                })?;
+                curr_environment
+                    .borrow_mut()
+                    .define(&name.lexeme, Some(value));
+            }
+        }
+        Stmt::Match {
+            keyword,
+            subject,
+            arms,
+        } => {
+            let subject = evaluate(subject, environment)?;
+            let (arm, arm_environment) = select_arm(keyword, &subject, arms, environment)?;
+            execute(&arms[arm].body, &arm_environment)?;
+        }
+        Stmt::Throw { keyword, value } => {
+            let value = evaluate(value, environment)?;
+
+            return Err(InterpreterSignal::Throw {
+                value,
+                token: keyword.clone(),
+            });
+        }
+        Stmt::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            let mut result = execute_block(body, &new_scope(environment));
+
+            if let Some((name, catch_body)) = catch {
+                let run_catch = |caught: LiteralType| {
+                    let catch_environment = new_scope(environment);
+                    catch_environment
+                        .environment
+                        .borrow_mut()
+                        .define(&name.lexeme, Some(caught));
+                    execute_block(catch_body, &catch_environment)
+                };
+
+                // break, continue and return aren't exceptions, they go through untouched
+                result = match result {
+                    Err(InterpreterSignal::Throw { value, .. }) => run_catch(value),
+                    Err(InterpreterSignal::RuntimeError(err)) => run_catch(error_value(err)),
+                    other => other,
+                };
+            }
+
+            // finally always runs. A signal coming out of it replaces the pending one
+            if let Some(finally) = finally {
+                execute_block(finally, &new_scope(environment))?;
+            }
+
+            result?;
+        }
+        Stmt::Yield { .. } => {
+            unreachable!("Shouldn't happen. Generator bodies are run by generator::resume")
+        }
     }
 
     Ok(())
 }
 
+// runs the module in its own environment the first time it's imported, later imports get the
+// cached one. Paths are relative to the file the import is in
+fn import_module(
+    keyword: &Token,
+    import: &str,
+    environment: &InterpreterEnvironment,
+) -> Result<Rc<Module>, InterpreterSignal> {
+    let modules = &environment.modules;
+    let base_dir = modules.borrow().base_dir(&environment.globals);
+
+    // canonical, so the same file imported through different relative paths only runs once
+    let Ok(path) = module::resolve_path(&base_dir, import).canonicalize() else {
+        Err(RuntimeError::new(
+            keyword,
+            format!("Module '{import}' not found."),
+        ))?
+    };
+
+    if modules.borrow().is_loading(&path) {
+        Err(RuntimeError::new(
+            keyword,
+            format!("Import cycle detected while importing '{import}'."),
+        ))?
+    }
+
+    if let Some(module) = modules.borrow().get(&path) {
+        return Ok(module);
+    }
+
+    let Ok(src) = fs::read_to_string(&path) else {
+        Err(RuntimeError::new(
+            keyword,
+            format!("Couldn't read module '{import}'."),
+        ))?
+    };
+
+    // static errors are reported by parse itself, like they are for the main file
+    let statements = crate::parse(&src, &environment.locals).map_err(|err| {
+        let message = match err {
+            RunError::OtherError(err) => format!("Couldn't load module '{import}'. {err}"),
+            _ => format!("Couldn't load module '{import}'."),
+        };
+        RuntimeError::new(keyword, message)
+    })?;
+
+    let module_environment = Rc::new(RefCell::new(Environment::new()));
+    let module = Rc::new(Module::new(path.clone(), Rc::clone(&module_environment)));
+
+    // cached before it runs, so imports inside of it are relative to its own directory
+    modules.borrow_mut().insert(Rc::clone(&module));
+    modules.borrow_mut().start_loading(path.clone());
+    let result = interpret(
+        &statements,
+        &module_environment,
+        &environment.locals,
+        modules,
+    );
+    modules.borrow_mut().finish_loading();
+
+    // a module that failed halfway isn't cached, importing it again runs it again
+    if result.is_err() {
+        modules.borrow_mut().remove(&path);
+    }
+    result?;
+
+    Ok(module)
+}
+
+// the first arm whose pattern matches and whose guard passes, together with the environment its
+// bindings are defined in
+pub fn select_arm<T>(
+    keyword: &Token,
+    subject: &LiteralType,
+    arms: &[MatchArm<T>],
+    environment: &InterpreterEnvironment,
+) -> Result<(usize, InterpreterEnvironment), InterpreterSignal> {
+    for (i, arm) in arms.iter().enumerate() {
+        let mut bindings = Vec::new();
+        if match_pattern(&arm.pattern, subject, &mut bindings).is_err() {
+            continue;
+        }
+
+        let arm_environment = new_scope(environment);
+        for (name, value) in bindings {
+            arm_environment
+                .environment
+                .borrow_mut()
+                .define(&name, Some(value));
+        }
+
+        if let Some(guard) = &arm.guard
+            && !is_truthy(&evaluate(guard, &arm_environment)?)
+        {
+            continue;
+        }
+
+        return Ok((i, arm_environment));
+    }
+
+    Err(RuntimeError::new(
+        keyword,
+        format!("No match arm matches {subject}."),
+    ))?
+}
+
+// collects the bound names into bindings. They are only used when the whole pattern matched,
+// otherwise the error says why it didn't
+fn match_pattern(
+    pattern: &Pattern,
+    value: &LiteralType,
+    bindings: &mut Vec<(String, LiteralType)>,
+) -> Result<(), String> {
+    match pattern {
+        Pattern::Literal(literal) if is_equal(literal, value) => Ok(()),
+        Pattern::Literal(literal) => Err(format!("Expected {literal} but got {value}.")),
+        Pattern::Wildcard => Ok(()),
+        Pattern::Binding(name) => {
+            bindings.push((name.lexeme.clone(), value.clone()));
+            Ok(())
+        }
+        Pattern::Or(alternatives) => {
+            if alternatives
+                .iter()
+                .any(|alternative| match_pattern(alternative, value, bindings).is_ok())
+            {
+                return Ok(());
+            }
+            Err(format!("No alternative matches {value}."))
+        }
+        Pattern::List { elements, rest } => {
+            let LiteralType::List(list) = value else {
+                return Err(format!("Expected a list but got {value}."));
+            };
+            let list = list.borrow();
+
+            match rest {
+                Some(_) if list.len() < elements.len() => Err(format!(
+                    "Expected at least {} elements but got {}.",
+                    elements.len(),
+                    list.len()
+                ))?,
+                None if list.len() != elements.len() => Err(format!(
+                    "Expected {} elements but got {}.",
+                    elements.len(),
+                    list.len()
+                ))?,
+                _ => (),
+            }
+
+            for (element, value) in elements.iter().zip(list.iter()) {
+                match_pattern(element, value, bindings)?;
+            }
+
+            // the rest is a new list, so changing it doesn't change the matched one
+            if let Some(rest) = rest
+                && rest.lexeme != "_"
+            {
+                let rest_list = list[elements.len()..].to_vec();
+                bindings.push((
+                    rest.lexeme.clone(),
+                    LiteralType::List(Rc::new(RefCell::new(rest_list))),
+                ));
+            }
+            Ok(())
+        }
+        // instances are matched by their fields, so `{name}` works on both
+        Pattern::Map { entries } => {
+            for (key, pattern) in entries {
+                let entry = match (value, key) {
+                    (LiteralType::Map(map), _) => {
+                        MapKey::from_literal(key).and_then(|key| map.borrow().get(&key).cloned())
+                    }
+                    (LiteralType::Instance(instance), LiteralType::String(key)) => {
+                        instance.borrow().field(key)
+                    }
+                    (LiteralType::Instance(_), _) => None,
+                    _ => return Err(format!("Expected a map but got {value}.")),
+                };
+
+                let Some(entry) = entry else {
+                    return Err(format!("Key {key} not found."));
+                };
+                match_pattern(pattern, &entry, bindings)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+// a new environment enclosed by the current one. We just move it to a new InterpreterEnvironment
+// and clone the reference to globals, bcs outer environments might have the globals reference
+pub fn new_scope(environment: &InterpreterEnvironment) -> InterpreterEnvironment {
+    InterpreterEnvironment {
+        globals: Rc::clone(&environment.globals),
+        environment: Rc::new(RefCell::new(Environment::with_enclosing(
+            &environment.environment,
+        ))),
+        locals: Rc::clone(&environment.locals),
+        modules: Rc::clone(&environment.modules),
+    }
+}
+
+// the scope of one for-in iteration. Each one gets its own, so closures capture the value of
+// their iteration
+pub fn loop_scope(
+    name: &Token,
+    value: LiteralType,
+    environment: &InterpreterEnvironment,
+) -> InterpreterEnvironment {
+    let loop_environment = new_scope(environment);
+    loop_environment
+        .environment
+        .borrow_mut()
+        .define(&name.lexeme, Some(value));
+    loop_environment
+}
+
+// built-in runtime errors are caught as instances of an `Error` class with the message and the
+// line they happened on
+pub fn error_value(error: RuntimeError) -> LiteralType {
+    let class = Rc::new(Class::new("Error".to_string(), None, HashMap::new()));
+    let mut instance = Instance::new(class);
+
+    let line = error.token.line;
+    let field = |name: &str| Token::new(TokenType::Identifier, name, None, line);
+    instance.set(&field("message"), LiteralType::String(error.message));
+    instance.set(&field("line"), LiteralType::Int(line as i64));
+
+    LiteralType::Instance(Rc::new(RefCell::new(instance)))
+}
+
+// executes the statements in the given environment. The caller is responsible for creating the
+// new scope, so function calls can put the parameters and the body in the same environment
 pub fn execute_block(
     statements: &Vec<Stmt>,
     environment: &InterpreterEnvironment,
 ) -> Result<(), InterpreterSignal> {
-    let block_enviroment = Rc::new(RefCell::new(Environment::with_enclosing(
-        &environment.environment,
-    )));
-    // we just move the block_enviroment to a new InterpreterEnvironment and clone the reference to
-    // globals, bcs outer environments might have the globals reference
-    let environment = InterpreterEnvironment {
-        globals: Rc::clone(&environment.globals),
-        environment: block_enviroment,
-    };
     for stmt in statements {
-        execute(stmt, &environment)?;
+        execute(stmt, environment)?;
     }
 
     Ok(())
 }
 
-fn evaluate(expr: &Expr, environment: &InterpreterEnvironment) -> InterpreterResult {
+pub fn evaluate(expr: &Expr, environment: &InterpreterEnvironment) -> InterpreterResult {
     let curr_environment = &environment.environment;
     match expr {
         Expr::Ternary {
@@ -206,36 +657,133 @@ fn evaluate(expr: &Expr, environment: &InterpreterEnvironment) -> InterpreterRes
             op,
         ),
         Expr::Grouping { expression } => evaluate(expression, environment),
+        Expr::Interpolation { parts } => {
+            let mut string = String::new();
+            for part in parts {
+                // same formatting as print
+                string.push_str(&evaluate(part, environment)?.to_string());
+            }
+            Ok(LiteralType::String(string))
+        }
+        Expr::Lambda {
+            params,
+            body,
+            is_generator,
+            ..
+        } => Ok(LiteralType::Callable(Callable::Function {
+            name: None,
+            params: params.to_vec(),
+            body: Rc::clone(body),
+            closure: Rc::clone(curr_environment),
+            is_initializer: false,
+            is_generator: *is_generator,
+        })),
         Expr::Literal { value } => Ok(value.clone()),
-        Expr::Unary { op, right } => Ok(unary(&evaluate(right, environment)?, op)),
-        Expr::Variable { name } => curr_environment
-            .borrow()
-            .get(name)
-            .ok_or_else(|| RuntimeError::new(name, format!("Undefined variable {}.", name.lexeme)))
-            .and_then(|x| {
-                x.ok_or_else(|| {
-                    RuntimeError::new(name, format!("Uninitialized variable {}.", name.lexeme))
-                })
-            })
-            .map_err(InterpreterSignal::RuntimeError),
-        Expr::Assign { name, value } => {
+        Expr::Map { brace, entries } => {
+            let mut map = Map::new();
+            for (key, value) in entries {
+                let key = map_key(&evaluate(key, environment)?, brace)?;
+                map.insert(key, evaluate(value, environment)?);
+            }
+            Ok(LiteralType::Map(Rc::new(RefCell::new(map))))
+        }
+        Expr::List { elements } => {
+            let mut values = Vec::new();
+            for element in elements {
+                values.push(evaluate(element, environment)?);
+            }
+            Ok(LiteralType::List(Rc::new(RefCell::new(values))))
+        }
+        Expr::Index {
+            object,
+            bracket,
+            index,
+        } => {
+            let object = evaluate(object, environment)?;
+            let index = evaluate(index, environment)?;
+            Ok(get_index(&object, &index, bracket)?)
+        }
+        Expr::SetIndex {
+            object,
+            bracket,
+            index,
+            value,
+        } => {
+            let object = evaluate(object, environment)?;
+            let index = evaluate(index, environment)?;
             let value = evaluate(value, environment)?;
-            curr_environment
-                .borrow_mut()
-                .assign(name, value.clone())
-                .map_err(|_| {
-                    RuntimeError::new(name, format!("Undefined variable {}.", name.lexeme))
-                })?;
+            set_index(&object, &index, value.clone(), bracket)?;
+            Ok(value)
+        }
+        Expr::CompoundAssign {
+            target,
+            op,
+            value,
+            postfix,
+        } => {
+            // every part of the target is evaluated once, then read, combined and written back
+            let (old, new) = match target.as_ref() {
+                Expr::Variable { id, name } => {
+                    let old = look_up_variable(id, name, environment)?;
+                    let new = binary(&old, &evaluate(value, environment)?, op)?;
+                    assign_variable(id, name, new.clone(), environment)?;
+                    (old, new)
+                }
+                Expr::Get { object, name } => {
+                    let LiteralType::Instance(instance) = evaluate(object, environment)? else {
+                        Err(RuntimeError::new(
+                            name,
+                            "Only instances have fields.".to_string(),
+                        ))?
+                    };
+                    let old = Instance::get(&instance, name)?;
+                    let new = binary(&old, &evaluate(value, environment)?, op)?;
+                    instance.borrow_mut().set(name, new.clone());
+                    (old, new)
+                }
+                Expr::Index {
+                    object,
+                    bracket,
+                    index,
+                } => {
+                    let object = evaluate(object, environment)?;
+                    let index = evaluate(index, environment)?;
+                    let old = get_index(&object, &index, bracket)?;
+                    let new = binary(&old, &evaluate(value, environment)?, op)?;
+                    set_index(&object, &index, new.clone(), bracket)?;
+                    (old, new)
+                }
+                _ => unreachable!("The parser only allows variables, properties and indices"),
+            };
+
+            Ok(if *postfix { old } else { new })
+        }
+        Expr::Unary { op, right } => unary(&evaluate(right, environment)?, op),
+        Expr::Variable { id, name } => look_up_variable(id, name, environment),
+        Expr::Assign { id, name, value } => {
+            let value = evaluate(value, environment)?;
+            assign_variable(id, name, value.clone(), environment)?;
+            Ok(value)
+        }
+        Expr::AssignList {
+            target,
+            equals,
+            value,
+        } => {
+            let value = evaluate(value, environment)?;
+            assign_target(target, value.clone(), equals, environment)?;
             Ok(value)
         }
         Expr::Logical { left, op, right } => {
             let left = evaluate(left, environment)?;
 
-            if op.t_type == TokenType::OR {
-                if is_truthy(&left) {
-                    return Ok(left);
-                }
-            } else if !is_truthy(&left) {
+            let short_circuits = match op.t_type {
+                TokenType::OR => is_truthy(&left),
+                // `??` only skips the right side for nil, false is kept
+                TokenType::QuestionQuestion => !matches!(left, LiteralType::Nil),
+                _ => !is_truthy(&left),
+            };
+            if short_circuits {
                 return Ok(left);
             }
 
@@ -245,6 +793,7 @@ fn evaluate(expr: &Expr, environment: &InterpreterEnvironment) -> InterpreterRes
             callee,
             paren,
             args,
+            named,
         } => {
             let callee_result = evaluate(callee, environment)?;
 
@@ -253,37 +802,349 @@ fn evaluate(expr: &Expr, environment: &InterpreterEnvironment) -> InterpreterRes
                 arguments.push(evaluate(arg, environment)?);
             }
 
-            match callee_result {
-                LiteralType::Callable(function) => {
-                    if arguments.len() as u8 != function.arity() {
-                        Err(RuntimeError::new(
-                            paren,
-                            format!(
-                                "Expected {} arguments but got {}.",
-                                function.arity(),
-                                args.len()
-                            ),
-                        ))?
-                    }
-                    let call_result = function.call(&arguments, environment).map_err(|x| match x {
-                        InterpreterSignal::NativeRuntimeError { msg } => {
-                            InterpreterSignal::RuntimeError(RuntimeError {
-                                token: paren.clone(),
-                                message: msg,
-                            })
-                        }
-                        _ => x,
-                    });
-
-                    Ok(call_result?)
+            if !named.is_empty() {
+                let mut named_arguments = Vec::new();
+                for (name, value) in named {
+                    named_arguments.push((name.clone(), evaluate(value, environment)?));
                 }
+
+                return match &callee_result {
+                    LiteralType::Callable(function) => {
+                        function.call_with_named(&arguments, &named_arguments, paren, environment)
+                    }
+                    LiteralType::Class(class) => {
+                        class.call_with_named(&arguments, &named_arguments, paren, environment)
+                    }
+                    _ => Err(RuntimeError::new(
+                        paren,
+                        "Can only call functions and classes".to_string(),
+                    ))?,
+                };
+            }
+
+            let function: &dyn CallableTrait = match &callee_result {
+                LiteralType::Callable(function) => function,
+                LiteralType::Class(class) => class,
                 _ => Err(RuntimeError::new(
                     paren,
                     "Can only call functions and classes".to_string(),
                 ))?,
+            };
+
+            let arity = function.arity();
+            if !arity.accepts(arguments.len()) {
+                Err(RuntimeError::new(
+                    paren,
+                    format!("Expected {arity} arguments but got {}.", args.len()),
+                ))?
+            }
+            let call_result = function.call(&arguments, environment).map_err(|x| match x {
+                InterpreterSignal::NativeRuntimeError { msg } => {
+                    InterpreterSignal::RuntimeError(RuntimeError {
+                        token: paren.clone(),
+                        message: msg,
+                    })
+                }
+                _ => x,
+            });
+
+            Ok(call_result?)
+        }
+        Expr::Get { object, name } => match evaluate(object, environment)? {
+            LiteralType::Instance(instance) => Ok(Instance::get(&instance, name)?),
+            LiteralType::Module(module) => module.get(&name.lexeme).ok_or_else(|| {
+                RuntimeError::new(
+                    name,
+                    format!("Module {} has no binding {}.", module.name, name.lexeme),
+                )
+                .into()
+            }),
+            LiteralType::Generator(generator) => Ok(Generator::get(&generator, name)?),
+            LiteralType::Range(range) => Ok(Range::get(&range, name)?),
+            _ => Err(RuntimeError::new(
+                name,
+                "Only instances have properties.".to_string(),
+            ))?,
+        },
+        Expr::Set {
+            object,
+            name,
+            value,
+        } => {
+            let LiteralType::Instance(instance) = evaluate(object, environment)? else {
+                Err(RuntimeError::new(
+                    name,
+                    "Only instances have fields.".to_string(),
+                ))?
+            };
+
+            let value = evaluate(value, environment)?;
+            instance.borrow_mut().set(name, value.clone());
+            Ok(value)
+        }
+        Expr::Super {
+            id,
+            keyword,
+            method,
+        } => {
+            // the resolver always puts `this` one environment inside of `super`
+            let distance = environment.locals.borrow().get(id).copied();
+            let Some(distance) = distance else {
+                Err(RuntimeError::new(
+                    keyword,
+                    "Can't use 'super' outside of a subclass.".to_string(),
+                ))?
+            };
+
+            let curr_environment = curr_environment.borrow();
+            let superclass = curr_environment.get_at(distance, keyword).flatten();
+            let this = Token::new(TokenType::This, "this", None, keyword.line);
+            let object = curr_environment.get_at(distance - 1, &this).flatten();
+
+            match (superclass, object) {
+                (Some(LiteralType::Class(superclass)), Some(LiteralType::Instance(instance))) => {
+                    let method = superclass.find_method(&method.lexeme).ok_or_else(|| {
+                        RuntimeError::new(method, format!("Undefined property {}.", method.lexeme))
+                    })?;
+                    Ok(LiteralType::Callable(method.bind(&instance)))
+                }
+                _ => Err(RuntimeError::new(
+                    keyword,
+                    "Can't use 'super' outside of a subclass.".to_string(),
+                ))?,
+            }
+        }
+        Expr::This { id, keyword } => look_up_variable(id, keyword, environment),
+        Expr::NilGuard { expression } => match evaluate(expression, environment)? {
+            LiteralType::Nil => Err(InterpreterSignal::ShortCircuit),
+            value => Ok(value),
+        },
+        Expr::OptionalChain { expression } => match evaluate(expression, environment) {
+            Err(InterpreterSignal::ShortCircuit) => Ok(LiteralType::Nil),
+            result => result,
+        },
+        Expr::Match {
+            keyword,
+            subject,
+            arms,
+        } => {
+            let subject = evaluate(subject, environment)?;
+            let (arm, arm_environment) = select_arm(keyword, &subject, arms, environment)?;
+            evaluate(&arms[arm].body, &arm_environment)
+        }
+    }
+}
+
+fn look_up_variable(
+    id: &ExprId,
+    name: &Token,
+    environment: &InterpreterEnvironment,
+) -> InterpreterResult {
+    let distance = environment.locals.borrow().get(id).copied();
+    let value = match distance {
+        Some(distance) => environment.environment.borrow().get_at(distance, name),
+        None => environment.globals.borrow().get(name),
+    };
+
+    value
+        .ok_or_else(|| RuntimeError::new(name, format!("Undefined variable {}.", name.lexeme)))
+        .and_then(|x| {
+            x.ok_or_else(|| {
+                RuntimeError::new(name, format!("Uninitialized variable {}.", name.lexeme))
+            })
+        })
+        .map_err(InterpreterSignal::RuntimeError)
+}
+
+fn assign_variable(
+    id: &ExprId,
+    name: &Token,
+    value: LiteralType,
+    environment: &InterpreterEnvironment,
+) -> Result<(), RuntimeError> {
+    let distance = environment.locals.borrow().get(id).copied();
+    match distance {
+        Some(distance) => environment
+            .environment
+            .borrow_mut()
+            .assign_at(distance, name, value),
+        None => environment.globals.borrow_mut().assign(name, value),
+    }
+    .map_err(|err| {
+        let message = match err {
+            EnvironmentError::AssignError => format!("Undefined variable {}.", name.lexeme),
+            EnvironmentError::ConstantAssignError => {
+                format!("Cannot assign to constant {}.", name.lexeme)
+            }
+        };
+        RuntimeError::new(name, message)
+    })
+}
+
+// one element of a destructuring assignment. Lists are taken apart element by element, the
+// lengths have to be equal
+fn assign_target(
+    target: &Expr,
+    value: LiteralType,
+    equals: &Token,
+    environment: &InterpreterEnvironment,
+) -> Result<(), InterpreterSignal> {
+    match target {
+        Expr::Variable { id, name } => assign_variable(id, name, value, environment)?,
+        Expr::Get { object, name } => {
+            let LiteralType::Instance(instance) = evaluate(object, environment)? else {
+                Err(RuntimeError::new(
+                    name,
+                    "Only instances have fields.".to_string(),
+                ))?
+            };
+            instance.borrow_mut().set(name, value);
+        }
+        Expr::Index {
+            object,
+            bracket,
+            index,
+        } => {
+            let object = evaluate(object, environment)?;
+            let index = evaluate(index, environment)?;
+            set_index(&object, &index, value, bracket)?;
+        }
+        Expr::List { elements } => {
+            let LiteralType::List(list) = value else {
+                Err(RuntimeError::new(
+                    equals,
+                    format!("Can't destructure: Expected a list but got {value}."),
+                ))?
+            };
+
+            // copied first, the targets might be elements of the same list
+            let values = list.borrow().clone();
+            if values.len() != elements.len() {
+                Err(RuntimeError::new(
+                    equals,
+                    format!(
+                        "Can't destructure: Expected {} elements but got {}.",
+                        elements.len(),
+                        values.len()
+                    ),
+                ))?
+            }
+
+            for (element, value) in elements.iter().zip(values) {
+                assign_target(element, value, equals, environment)?;
+            }
+        }
+        _ => unreachable!("Shouldn't happen. The parser only allows assignable targets"),
+    }
+
+    Ok(())
+}
+
+fn get_index(
+    object: &LiteralType,
+    index: &LiteralType,
+    bracket: &Token,
+) -> Result<LiteralType, RuntimeError> {
+    match object {
+        LiteralType::List(list) => {
+            let list = list.borrow();
+            let index = list_index(index, list.len(), bracket)?;
+            Ok(list[index].clone())
+        }
+        LiteralType::Map(map) => {
+            let key = map_key(index, bracket)?;
+            map.borrow()
+                .get(&key)
+                .cloned()
+                .ok_or_else(|| RuntimeError::new(bracket, format!("Key {index} not found in map.")))
+        }
+        // `string[1..3]`, the bounds count characters, not bytes
+        LiteralType::String(string) => {
+            let LiteralType::Range(range) = index else {
+                Err(RuntimeError::new(
+                    bracket,
+                    "Strings can only be sliced with a range.".to_string(),
+                ))?
+            };
+
+            let len = string.chars().count() as i64;
+            let end = if range.inclusive {
+                range.end.saturating_add(1)
+            } else {
+                range.end
+            };
+            if range.step != 1 || range.start < 0 || range.start > end || end > len {
+                Err(RuntimeError::new(
+                    bracket,
+                    format!("Can't slice a string of length {len} with {range}."),
+                ))?
             }
+
+            Ok(LiteralType::String(
+                string.slice(range.start as usize..end as usize).to_string(),
+            ))
+        }
+        _ => Err(RuntimeError::new(
+            bracket,
+            "Only lists, maps and strings can be indexed.".to_string(),
+        )),
+    }
+}
+
+fn set_index(
+    object: &LiteralType,
+    index: &LiteralType,
+    value: LiteralType,
+    bracket: &Token,
+) -> Result<(), RuntimeError> {
+    match object {
+        LiteralType::List(list) => {
+            let mut list = list.borrow_mut();
+            let index = list_index(index, list.len(), bracket)?;
+            list[index] = value;
+            Ok(())
+        }
+        LiteralType::Map(map) => {
+            let key = map_key(index, bracket)?;
+            map.borrow_mut().insert(key, value);
+            Ok(())
         }
+        LiteralType::String(_) => Err(RuntimeError::new(
+            bracket,
+            "Strings can't be changed by index.".to_string(),
+        )),
+        _ => Err(RuntimeError::new(
+            bracket,
+            "Only lists and maps can be indexed.".to_string(),
+        )),
+    }
+}
+
+fn list_index(index: &LiteralType, len: usize, bracket: &Token) -> Result<usize, RuntimeError> {
+    let Some(index) = as_integer(index) else {
+        return Err(RuntimeError::new(
+            bracket,
+            "List index must be an integer.".to_string(),
+        ));
+    };
+
+    if index < 0 || index >= len as i64 {
+        return Err(RuntimeError::new(
+            bracket,
+            format!("List index {index} out of range for length {len}."),
+        ));
     }
+
+    Ok(index as usize)
+}
+
+fn map_key(key: &LiteralType, token: &Token) -> Result<MapKey, RuntimeError> {
+    MapKey::from_literal(key).ok_or_else(|| {
+        RuntimeError::new(
+            token,
+            "Map keys must be strings, numbers, booleans or nil.".to_string(),
+        )
+    })
 }
 
 fn ternary(
@@ -300,31 +1161,58 @@ fn ternary(
 }
 
 fn binary(left: &LiteralType, right: &LiteralType, op: &Token) -> InterpreterResult {
-    use LiteralType::{Bool, Number, String};
+    use LiteralType::{Bool, Int, Number, String};
     use TokenType::{
-        BangEqual, Comma, EqualEqual, Greater, GreaterEqual, Less, LessEqual, Minus, Plus, Slash,
-        Star,
+        Ampersand, BangEqual, Caret, Comma, DotDot, DotDotEqual, EqualEqual, Greater, GreaterEqual,
+        GreaterGreater, In, Less, LessEqual, LessLess, Minus, Percent, Pipe, Plus, Slash, Star,
+        StarStar, TildeSlash,
     };
 
     match (op.t_type, &left, &right) {
-        (Greater, Number(left), Number(right)) => Ok(Bool(left > right)),
-        (GreaterEqual, Number(left), Number(right)) => Ok(Bool(left >= right)),
-        (Less, Number(left), Number(right)) => Ok(Bool(left < right)),
-        (LessEqual, Number(left), Number(right)) => Ok(Bool(left <= right)),
         (BangEqual, _, _) => Ok(Bool(!is_equal(left, right))),
         (EqualEqual, _, _) => Ok(Bool(is_equal(left, right))),
-        (Minus, Number(left), Number(right)) => Ok(Number(left - right)),
-        (Plus, Number(left), Number(right)) => Ok(Number(left + right)),
+        (In, _, _) => Ok(Bool(contains(right, left, op)?)),
+        (DotDot | DotDotEqual, _, _) => match (as_integer(left), as_integer(right)) {
+            (Some(start), Some(end)) => Ok(LiteralType::Range(Range::new(
+                start,
+                end,
+                op.t_type == DotDotEqual,
+            ))),
+            _ => Err(RuntimeError::new(
+                op,
+                "Range bounds must be integers.".to_string(),
+            ))?,
+        },
+        /* comma operator discard the left operand, so we just return the evaluation of the right operand */
+        (Comma, _, _) => Ok(right.clone()),
         (Plus, String(left), String(right)) => Ok(String(format!("{left}{right}"))),
         (Plus, String(left), Number(right)) => Ok(String(format!("{left}{right}"))),
         (Plus, Number(left), String(right)) => Ok(String(format!("{left}{right}"))),
-        (Slash, Number(left), Number(right)) => Ok(Number(left / right)),
-        (Star, Number(left), Number(right)) => Ok(Number(left * right)),
-        /* comma operator discard the left operand, so we just return the evaluation of the right operand */
-        (Comma, _, _) => Ok(right.clone()),
-        (Greater | GreaterEqual | Less | LessEqual | Minus | Slash | Star, _, _) => Err(
-            RuntimeError::new(op, "Operands must be numbers".to_string()),
-        )?,
+        (Plus, String(left), Int(right)) => Ok(String(format!("{left}{right}"))),
+        (Plus, Int(left), String(right)) => Ok(String(format!("{left}{right}"))),
+        (Ampersand | Pipe | Caret | LessLess | GreaterGreater, _, _) => bitwise(left, right, op),
+        (_, Int(left), Int(right)) => int_arithmetic(*left, *right, op),
+        // compared exactly, like ==. Promoting the int would round it above 2^53
+        (Greater | GreaterEqual | Less | LessEqual, Int(left), Number(right)) => {
+            Ok(Bool(compare(compare_int_float(*left, *right), op)))
+        }
+        (Greater | GreaterEqual | Less | LessEqual, Number(left), Int(right)) => {
+            let ordering = compare_int_float(*right, *left).map(Ordering::reverse);
+            Ok(Bool(compare(ordering, op)))
+        }
+        // mixing an int with a float promotes the int
+        (_, Int(_) | Number(_), Int(_) | Number(_)) => {
+            float_arithmetic(as_float(left), as_float(right), op)
+        }
+        (
+            Greater | GreaterEqual | Less | LessEqual | Minus | Slash | Star | Percent | TildeSlash
+            | StarStar,
+            _,
+            _,
+        ) => Err(RuntimeError::new(
+            op,
+            "Operands must be numbers".to_string(),
+        ))?,
         (Plus, _, _) => Err(RuntimeError::new(
             op,
             "Operands must be two numbers or two strings".to_string(),
@@ -336,15 +1224,227 @@ fn binary(left: &LiteralType, right: &LiteralType, op: &Token) -> InterpreterRes
     }
 }
 
-fn unary(right: &LiteralType, op: &Token) -> LiteralType {
-    match (op.t_type, &right) {
-        (TokenType::Minus, LiteralType::Number(num)) => LiteralType::Number(-num),
-        (TokenType::Bang, _) => LiteralType::Bool(!is_truthy(right)),
-        _ => unreachable!("Shouldn't happen. Expr::Unary for evaluate"),
-    }
+// `value in collection`. Maps are searched by key and strings by substring
+fn contains(
+    collection: &LiteralType,
+    value: &LiteralType,
+    op: &Token,
+) -> Result<bool, RuntimeError> {
+    match (collection, value) {
+        // only integers can be in a range, 1.5 never is
+        (LiteralType::Range(range), value) => {
+            Ok(as_integer(value).is_some_and(|value| range.contains(value)))
+        }
+        (LiteralType::List(list), value) => {
+            Ok(list.borrow().iter().any(|element| is_equal(element, value)))
+        }
+        (LiteralType::Map(map), value) => {
+            Ok(MapKey::from_literal(value).is_some_and(|key| map.borrow().contains_key(&key)))
+        }
+        (LiteralType::String(string), LiteralType::String(value)) => Ok(string.contains(value)),
+        (LiteralType::String(_), _) => Err(RuntimeError::new(
+            op,
+            "Only strings can be in a string.".to_string(),
+        )),
+        _ => Err(RuntimeError::new(
+            op,
+            "Right operand of 'in' must be a range, a list, a map or a string.".to_string(),
+        )),
+    }
+}
+
+fn float_arithmetic(left: f64, right: f64, op: &Token) -> InterpreterResult {
+    use LiteralType::{Bool, Number};
+    use TokenType::{
+        Greater, GreaterEqual, Less, LessEqual, Minus, Percent, Plus, Slash, Star, StarStar,
+        TildeSlash,
+    };
+
+    match op.t_type {
+        Greater => Ok(Bool(left > right)),
+        GreaterEqual => Ok(Bool(left >= right)),
+        Less => Ok(Bool(left < right)),
+        LessEqual => Ok(Bool(left <= right)),
+        Minus => Ok(Number(left - right)),
+        Plus => Ok(Number(left + right)),
+        Slash => Ok(Number(left / right)),
+        Star => Ok(Number(left * right)),
+        // floored, so the result has the sign of the divisor and matches ~/
+        Percent => Ok(Number(left - right * (left / right).floor())),
+        TildeSlash => Ok(Number((left / right).floor())),
+        StarStar => Ok(Number(left.powf(right))),
+        _ => unreachable!("Shouldn't happen. Not an arithmetic operator"),
+    }
+}
+
+// int op int stays an int, except for `/` which always gives a float and `**` with a negative
+// exponent. Anything that doesn't fit in an i64 is an error instead of silently wrapping
+fn int_arithmetic(left: i64, right: i64, op: &Token) -> InterpreterResult {
+    use LiteralType::{Bool, Int, Number};
+    use TokenType::{
+        Greater, GreaterEqual, Less, LessEqual, Minus, Percent, Plus, Slash, Star, StarStar,
+        TildeSlash,
+    };
+
+    let result = match op.t_type {
+        Greater => return Ok(Bool(left > right)),
+        GreaterEqual => return Ok(Bool(left >= right)),
+        Less => return Ok(Bool(left < right)),
+        LessEqual => return Ok(Bool(left <= right)),
+        Slash => return Ok(Number(left as f64 / right as f64)),
+        StarStar if right < 0 => return Ok(Number((left as f64).powf(right as f64))),
+        Percent | TildeSlash if right == 0 => {
+            Err(RuntimeError::new(op, "Division by zero".to_string()))?
+        }
+        Minus => left.checked_sub(right),
+        Plus => left.checked_add(right),
+        Star => left.checked_mul(right),
+        // the remainder can't overflow, only i64::MIN % -1 wraps and it wraps to the right 0
+        Percent => {
+            let rem = left.wrapping_rem_euclid(right);
+            // rem_euclid is always positive, floored modulo takes the sign of the divisor
+            Some(if rem != 0 && right < 0 {
+                rem + right
+            } else {
+                rem
+            })
+        }
+        TildeSlash => left.checked_div(right).map(|quot| {
+            if (left % right != 0) && ((left < 0) != (right < 0)) {
+                quot - 1
+            } else {
+                quot
+            }
+        }),
+        StarStar => u32::try_from(right)
+            .ok()
+            .and_then(|exp| left.checked_pow(exp)),
+        _ => unreachable!("Shouldn't happen. Not an arithmetic operator"),
+    };
+
+    let Some(result) = result else {
+        Err(RuntimeError::new(op, "Integer overflow".to_string()))?
+    };
+
+    Ok(Int(result))
 }
 
-fn is_truthy(literal: &LiteralType) -> bool {
+// None when the float is NaN, which is neither less, greater nor equal
+fn compare_int_float(int: i64, float: f64) -> Option<Ordering> {
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0; // 2^63
+
+    if float.is_nan() {
+        return None;
+    }
+    if float >= LIMIT {
+        return Some(Ordering::Less);
+    }
+    if float < -LIMIT {
+        return Some(Ordering::Greater);
+    }
+
+    // in range, so the integral part converts without rounding and only the fraction is left to
+    // break a tie
+    let ordering = int.cmp(&(float.trunc() as i64));
+    Some(ordering.then(0.0.partial_cmp(&float.fract())?))
+}
+
+fn compare(ordering: Option<Ordering>, op: &Token) -> bool {
+    let Some(ordering) = ordering else {
+        return false;
+    };
+
+    match op.t_type {
+        TokenType::Greater => ordering.is_gt(),
+        TokenType::GreaterEqual => ordering.is_ge(),
+        TokenType::Less => ordering.is_lt(),
+        TokenType::LessEqual => ordering.is_le(),
+        _ => unreachable!("Shouldn't happen. Not a comparison operator"),
+    }
+}
+
+fn as_float(num: &LiteralType) -> f64 {
+    match num {
+        LiteralType::Int(i) => *i as f64,
+        LiteralType::Number(n) => *n,
+        _ => unreachable!("Shouldn't happen. Only numbers are converted to floats"),
+    }
+}
+
+fn unary(right: &LiteralType, op: &Token) -> InterpreterResult {
+    match (op.t_type, &right) {
+        (TokenType::Minus, LiteralType::Number(num)) => Ok(LiteralType::Number(-num)),
+        (TokenType::Minus, LiteralType::Int(num)) => {
+            let num = num
+                .checked_neg()
+                .ok_or_else(|| RuntimeError::new(op, "Integer overflow".to_string()))?;
+            Ok(LiteralType::Int(num))
+        }
+        (TokenType::Bang, _) => Ok(LiteralType::Bool(!is_truthy(right))),
+        (TokenType::Tilde, _) => {
+            let num = as_integer(right)
+                .ok_or_else(|| RuntimeError::new(op, "Operand must be an integer".to_string()))?;
+            Ok(LiteralType::Int(!num))
+        }
+        (TokenType::Minus, _) => Err(RuntimeError::new(
+            op,
+            "Operand must be a number".to_string(),
+        ))?,
+        _ => unreachable!("Shouldn't happen. Expr::Unary for evaluate"),
+    }
+}
+
+// the integral value of a number. Floats with a fractional part or outside of the i64 range
+// have none
+pub fn as_integer(num: &LiteralType) -> Option<i64> {
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0; // 2^63
+
+    match num {
+        LiteralType::Int(i) => Some(*i),
+        LiteralType::Number(n) if n.fract() == 0.0 && (-LIMIT..LIMIT).contains(n) => {
+            Some(*n as i64)
+        }
+        _ => None,
+    }
+}
+
+// bitwise operators work on the integral value of a number and always give an int back
+fn bitwise(left: &LiteralType, right: &LiteralType, op: &Token) -> InterpreterResult {
+    use TokenType::{Ampersand, Caret, GreaterGreater, LessLess, Pipe};
+
+    let (Some(left), Some(right)) = (as_integer(left), as_integer(right)) else {
+        Err(RuntimeError::new(
+            op,
+            "Operands must be integers".to_string(),
+        ))?
+    };
+
+    let result = match op.t_type {
+        Ampersand => left & right,
+        Pipe => left | right,
+        Caret => left ^ right,
+        LessLess | GreaterGreater => {
+            let shift = u32::try_from(right).ok().filter(|shift| *shift < 64);
+            let Some(shift) = shift else {
+                Err(RuntimeError::new(
+                    op,
+                    "Shift amount must be between 0 and 63".to_string(),
+                ))?
+            };
+
+            if op.t_type == LessLess {
+                left << shift
+            } else {
+                left >> shift
+            }
+        }
+        _ => unreachable!("Shouldn't happen. Not a bitwise operator"),
+    };
+
+    Ok(LiteralType::Int(result))
+}
+
+pub fn is_truthy(literal: &LiteralType) -> bool {
     match literal {
         LiteralType::Nil => false,
         LiteralType::Bool(val) => *val,
@@ -352,6 +1452,25 @@ fn is_truthy(literal: &LiteralType) -> bool {
     }
 }
 
+thread_local! {
+    // the pairs of containers that are being compared right now. Running into a pair again means
+    // both sides contain themselves the same way, nothing else in them can differ from there on
+    static COMPARING: RefCell<Vec<(*const (), *const ())>> = const { RefCell::new(Vec::new()) };
+}
+
+// like token::print_once, a pair that is already being compared counts as equal
+fn compare_once(left: *const (), right: *const (), compare: impl FnOnce() -> bool) -> bool {
+    let pair = (left, right);
+    if COMPARING.with_borrow(|comparing| comparing.contains(&pair)) {
+        return true;
+    }
+
+    COMPARING.with_borrow_mut(|comparing| comparing.push(pair));
+    let equal = compare();
+    COMPARING.with_borrow_mut(|comparing| comparing.pop());
+    equal
+}
+
 pub fn is_equal(left: &LiteralType, right: &LiteralType) -> bool {
     match (left, right) {
         (LiteralType::Nil, LiteralType::Nil) => true,
@@ -359,7 +1478,37 @@ pub fn is_equal(left: &LiteralType, right: &LiteralType) -> bool {
         // i could've implemeneted PartialEq but it doesn't make sense for every LiteralType
         (LiteralType::String(s), LiteralType::String(s2)) => s == s2,
         (LiteralType::Number(n1), LiteralType::Number(n2)) => n1 == n2,
+        (LiteralType::Int(i1), LiteralType::Int(i2)) => i1 == i2,
+        // 1 == 1.0, but only when the float is exactly integral
+        (LiteralType::Int(i), n @ LiteralType::Number(_))
+        | (n @ LiteralType::Number(_), LiteralType::Int(i)) => as_integer(n) == Some(*i),
         (LiteralType::Bool(t1), LiteralType::Bool(t2)) => t1 == t2,
+        // classes, instances, modules and generators are compared by identity
+        (LiteralType::Class(c1), LiteralType::Class(c2)) => Rc::ptr_eq(c1, c2),
+        (LiteralType::Instance(i1), LiteralType::Instance(i2)) => Rc::ptr_eq(i1, i2),
+        (LiteralType::Module(m1), LiteralType::Module(m2)) => Rc::ptr_eq(m1, m2),
+        (LiteralType::Generator(g1), LiteralType::Generator(g2)) => Rc::ptr_eq(g1, g2),
+        (LiteralType::Range(r1), LiteralType::Range(r2)) => r1 == r2,
+        // lists are compared element by element. The same list is always equal to itself, which
+        // also keeps a list that contains itself from recursing forever
+        (LiteralType::List(l1), LiteralType::List(l2)) if Rc::ptr_eq(l1, l2) => true,
+        (LiteralType::List(l1), LiteralType::List(l2)) => {
+            compare_once(Rc::as_ptr(l1).cast(), Rc::as_ptr(l2).cast(), || {
+                let (l1, l2) = (l1.borrow(), l2.borrow());
+                l1.len() == l2.len() && l1.iter().zip(l2.iter()).all(|(a, b)| is_equal(a, b))
+            })
+        }
+        // maps are equal when they have the same keys with equal values, order doesn't matter
+        (LiteralType::Map(m1), LiteralType::Map(m2)) if Rc::ptr_eq(m1, m2) => true,
+        (LiteralType::Map(m1), LiteralType::Map(m2)) => {
+            compare_once(Rc::as_ptr(m1).cast(), Rc::as_ptr(m2).cast(), || {
+                let (m1, m2) = (m1.borrow(), m2.borrow());
+                m1.len() == m2.len()
+                    && m1
+                        .iter()
+                        .all(|(key, v1)| m2.get(key).is_some_and(|v2| is_equal(v1, v2)))
+            })
+        }
         _ => false,
     }
 }
@@ -381,3 +1530,1674 @@ fn read_input_function() -> NativeFunction {
 
     NativeFunction::new("read_input".to_string(), 0, read_input)
 }
+
+fn len_function() -> NativeFunction {
+    let len = |args: &[LiteralType]| match &args[0] {
+        LiteralType::String(s) => Ok(LiteralType::Int(s.chars().count() as i64)),
+        LiteralType::List(l) => Ok(LiteralType::Int(l.borrow().len() as i64)),
+        LiteralType::Map(m) => Ok(LiteralType::Int(m.borrow().len() as i64)),
+        LiteralType::Range(r) => match i64::try_from(r.len()) {
+            Ok(len) => Ok(LiteralType::Int(len)),
+            Err(_) => Err(InterpreterSignal::NativeRuntimeError {
+                msg: "Range is too long to have a length".to_string(),
+            }),
+        },
+        _ => Err(InterpreterSignal::NativeRuntimeError {
+            msg: "len() expects a string, a list, a map or a range".to_string(),
+        }),
+    };
+
+    NativeFunction::new("len".to_string(), 1, len)
+}
+
+fn push_function() -> NativeFunction {
+    let push = |args: &[LiteralType]| match &args[0] {
+        LiteralType::List(l) => {
+            l.borrow_mut().push(args[1].clone());
+            Ok(LiteralType::Nil)
+        }
+        _ => Err(InterpreterSignal::NativeRuntimeError {
+            msg: "push() expects a list as the first argument".to_string(),
+        }),
+    };
+
+    NativeFunction::new("push".to_string(), 2, push)
+}
+
+fn native_map_key(key: &LiteralType) -> Result<MapKey, InterpreterSignal> {
+    MapKey::from_literal(key).ok_or_else(|| InterpreterSignal::NativeRuntimeError {
+        msg: "Map keys must be strings, numbers, booleans or nil.".to_string(),
+    })
+}
+
+fn native_map(value: &LiteralType, name: &str) -> Result<Rc<RefCell<Map>>, InterpreterSignal> {
+    match value {
+        LiteralType::Map(m) => Ok(Rc::clone(m)),
+        _ => Err(InterpreterSignal::NativeRuntimeError {
+            msg: format!("{name}() expects a map as the first argument"),
+        }),
+    }
+}
+
+fn keys_function() -> NativeFunction {
+    let keys = |args: &[LiteralType]| {
+        let map = native_map(&args[0], "keys")?;
+        let keys = map.borrow().iter().map(|(k, _)| k.to_literal()).collect();
+        Ok(LiteralType::List(Rc::new(RefCell::new(keys))))
+    };
+
+    NativeFunction::new("keys".to_string(), 1, keys)
+}
+
+fn values_function() -> NativeFunction {
+    let values = |args: &[LiteralType]| {
+        let map = native_map(&args[0], "values")?;
+        let values = map.borrow().iter().map(|(_, v)| v.clone()).collect();
+        Ok(LiteralType::List(Rc::new(RefCell::new(values))))
+    };
+
+    NativeFunction::new("values".to_string(), 1, values)
+}
+
+fn has_function() -> NativeFunction {
+    let has = |args: &[LiteralType]| {
+        let map = native_map(&args[0], "has")?;
+        let key = native_map_key(&args[1])?;
+        Ok(LiteralType::Bool(map.borrow().contains_key(&key)))
+    };
+
+    NativeFunction::new("has".to_string(), 2, has)
+}
+
+// returns the removed value, or nil when the key wasn't there
+fn remove_function() -> NativeFunction {
+    let remove = |args: &[LiteralType]| {
+        let map = native_map(&args[0], "remove")?;
+        let key = native_map_key(&args[1])?;
+        Ok(map.borrow_mut().remove(&key).unwrap_or(LiteralType::Nil))
+    };
+
+    NativeFunction::new("remove".to_string(), 2, remove)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parser::Parser, resolver::Resolver, scanner::Scanner};
+
+    use super::*;
+
+    fn run(src: &str) -> (Rc<RefCell<Environment>>, Result<(), InterpreterSignal>) {
+        let mut scanner = Scanner::new(src.to_string());
+        let tokens = scanner
+            .scan_tokens()
+            .expect("There shouldn't be a scan error");
+        let statements: Vec<Stmt> = Parser::new(tokens)
+            .parse()
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .expect("There shouldn't be a parse error");
+        let locals = Resolver::new()
+            .resolve(&statements)
+            .expect("There shouldn't be a resolve error");
+
+        let environment = Rc::new(RefCell::new(Environment::new()));
+        let result = interpret(
+            &statements,
+            &environment,
+            &Rc::new(RefCell::new(locals)),
+            &Rc::new(RefCell::new(Modules::new())),
+        );
+
+        (environment, result)
+    }
+
+    fn run_ok(src: &str) -> Rc<RefCell<Environment>> {
+        let (environment, result) = run(src);
+        if let Err(err) = result {
+            panic!(
+                "There shouldn't be an error: {}",
+                RuntimeError::from(err).message
+            );
+        }
+        environment
+    }
+
+    fn run_err(src: &str) -> RuntimeError {
+        match run(src).1 {
+            Err(err) => err.into(),
+            Ok(_) => panic!("Should be an error"),
+        }
+    }
+
+    fn global(environment: &Rc<RefCell<Environment>>, name: &str) -> LiteralType {
+        let token = Token::new(TokenType::Identifier, name, None, 0);
+        environment
+            .borrow()
+            .get(&token)
+            .flatten()
+            .unwrap_or_else(|| panic!("Global {name} should be defined"))
+    }
+
+    fn assert_global(environment: &Rc<RefCell<Environment>>, name: &str, expected: LiteralType) {
+        let actual = global(environment, name);
+        assert!(
+            is_equal(&actual, &expected),
+            "{name}: expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn closure_ignores_later_shadowing() {
+        let environment = run_ok(
+            r#"
+            var a = "global";
+            var first;
+            var second;
+            {
+                fun show() { return a; }
+                first = show();
+                var a = "block";
+                second = show();
+            }"#,
+        );
+
+        assert_global(&environment, "first", LiteralType::string_literal("global"));
+        assert_global(
+            &environment,
+            "second",
+            LiteralType::string_literal("global"),
+        );
+    }
+
+    #[test]
+    fn class_fields_methods_and_initializer() {
+        let environment = run_ok(
+            r#"
+            class Counter {
+                init(start) {
+                    this.count = start;
+                }
+
+                increment() {
+                    this.count = this.count + 1;
+                    return this;
+                }
+            }
+
+            var counter = Counter(10);
+            counter.increment().increment();
+            var bound = counter.increment;
+            bound();
+            var count = counter.count;
+            var reinit = counter.init(0) == counter;"#,
+        );
+
+        assert_global(&environment, "count", LiteralType::number_literal(13.0));
+        assert_global(&environment, "reinit", LiteralType::Bool(true));
+    }
+
+    #[test]
+    fn inheritance_and_super_calls() {
+        let environment = run_ok(
+            r#"
+            class A {
+                init(name) { this.name = name; }
+                greet() { return "A " + this.name; }
+                only_a() { return "only a"; }
+            }
+
+            class B < A {
+                greet() { return "B " + super.greet(); }
+            }
+
+            class C < B {
+                init() { super.init("c"); }
+            }
+
+            var greeting = C().greet();
+            var inherited = C().only_a();"#,
+        );
+
+        assert_global(
+            &environment,
+            "greeting",
+            LiteralType::string_literal("B A c"),
+        );
+        assert_global(
+            &environment,
+            "inherited",
+            LiteralType::string_literal("only a"),
+        );
+    }
+
+    #[test]
+    fn error_superclass_not_a_class() {
+        let err = run_err("var A = 1; class B < A {}");
+
+        assert_eq!(err.message, "Superclass must be a class.");
+        assert_eq!(err.token.lexeme, "A");
+    }
+
+    #[test]
+    fn error_undefined_property() {
+        let err = run_err("class A {} A().missing;");
+
+        assert_eq!(err.message, "Undefined property missing.");
+    }
+
+    #[test]
+    fn error_property_on_non_instance() {
+        let err = run_err("var a = 1; a.field = 2;");
+
+        assert_eq!(err.message, "Only instances have fields.");
+    }
+
+    #[test]
+    fn continue_runs_for_increment() {
+        let environment = run_ok(
+            r#"
+            var sum = 0;
+            for (var i = 0; i < 5; i = i + 1) {
+                if (i == 2) continue;
+                sum = sum + i;
+            }
+
+            var skipped = 0;
+            var j = 0;
+            while (j < 5) {
+                j = j + 1;
+                if (j == 3) continue;
+                skipped = skipped + j;
+            }
+
+            var iterations = 0;
+            for (;;) {
+                iterations = iterations + 1;
+                if (iterations < 3) continue;
+                break;
+            }"#,
+        );
+
+        assert_global(&environment, "sum", LiteralType::number_literal(8.0));
+        assert_global(&environment, "skipped", LiteralType::number_literal(12.0));
+        assert_global(&environment, "iterations", LiteralType::number_literal(3.0));
+    }
+
+    #[test]
+    fn return_from_inside_loops() {
+        let environment = run_ok(
+            r#"
+            fun find(target) {
+                var i = 0;
+                while (true) {
+                    for (var j = 0; j < 10; j = j + 1) {
+                        if (i * 10 + j == target) return j;
+                    }
+                    i = i + 1;
+                }
+                return "fell through";
+            }
+
+            var after = "unchanged";
+            fun early() {
+                while (true) {
+                    return "returned";
+                }
+                after = "fell through";
+            }
+
+            var found = find(23);
+            var returned = early();"#,
+        );
+
+        assert_global(&environment, "found", LiteralType::number_literal(3.0));
+        assert_global(
+            &environment,
+            "returned",
+            LiteralType::string_literal("returned"),
+        );
+        assert_global(
+            &environment,
+            "after",
+            LiteralType::string_literal("unchanged"),
+        );
+    }
+
+    #[test]
+    fn error_inside_nested_loops() {
+        let err = run_err(
+            r#"
+            for (var i = 0; i < 3; i = i + 1) {
+                while (true) {
+                    var boom = "not a number" - 1;
+                }
+            }"#,
+        );
+
+        assert_eq!(err.message, "Operands must be numbers");
+    }
+
+    #[test]
+    fn error_inside_loop_stops_execution() {
+        let (environment, result) = run(r#"
+            var reached = false;
+            while (true) {
+                undefined_function();
+            }
+            reached = true;"#);
+
+        assert!(result.is_err());
+        assert_global(&environment, "reached", LiteralType::Bool(false));
+    }
+
+    #[test]
+    fn nested_function_declarations_are_local() {
+        let environment = run_ok(
+            r#"
+            fun helper() { return "global"; }
+
+            fun outer() {
+                fun helper() { return "local"; }
+                fun fib(n) {
+                    if (n < 2) return n;
+                    return fib(n - 1) + fib(n - 2);
+                }
+                return helper() + " " + fib(10);
+            }
+
+            var inner = outer();
+            var outer_helper = helper();"#,
+        );
+
+        assert_global(
+            &environment,
+            "inner",
+            LiteralType::string_literal("local 55"),
+        );
+        assert_global(
+            &environment,
+            "outer_helper",
+            LiteralType::string_literal("global"),
+        );
+    }
+
+    #[test]
+    fn function_declaration_in_block_doesnt_leak() {
+        let (environment, result) = run(r#"
+            var shadowed = "global";
+            {
+                fun shadowed() {}
+                fun block_only() {}
+            }
+            var kept = shadowed;
+            block_only();"#);
+
+        let Err(err) = result else {
+            panic!("Should be an error");
+        };
+        assert_eq!(
+            RuntimeError::from(err).message,
+            "Undefined variable block_only."
+        );
+        assert_global(&environment, "kept", LiteralType::string_literal("global"));
+    }
+
+    #[test]
+    fn lambdas_capture_their_environment() {
+        let environment = run_ok(
+            r#"
+            fun apply(f, x) { return f(x); }
+
+            fun make_adder(n) {
+                return fun (x) { return x + n; };
+            }
+
+            var add_two = make_adder(2);
+            var added = apply(add_two, 40);
+            var doubled = apply(fun (x) => x * 2, 21);
+            var immediate = (fun () => "called")();
+            fun () {};"#,
+        );
+
+        assert_global(&environment, "added", LiteralType::number_literal(42.0));
+        assert_global(&environment, "doubled", LiteralType::number_literal(42.0));
+        assert_global(
+            &environment,
+            "immediate",
+            LiteralType::string_literal("called"),
+        );
+    }
+
+    #[test]
+    fn list_literals_indexing_and_assignment() {
+        let environment = run_ok(
+            r#"
+            var xs = [1, "two", [3]];
+            var alias = xs;
+            alias[0] = 10;
+            xs[2][0] = xs[2][0] + 1;
+            push(xs, nil);
+
+            var first = xs[0];
+            var nested = xs[2][0];
+            var length = len(xs);
+            var empty = len([]);
+            var same = [1, [2]] == [1, [2]];"#,
+        );
+
+        assert_global(&environment, "first", LiteralType::number_literal(10.0));
+        assert_global(&environment, "nested", LiteralType::number_literal(4.0));
+        assert_global(&environment, "length", LiteralType::number_literal(4.0));
+        assert_global(&environment, "empty", LiteralType::number_literal(0.0));
+        assert_global(&environment, "same", LiteralType::Bool(true));
+    }
+
+    #[test]
+    fn error_list_index_out_of_range() {
+        let err = run_err("var xs = [1, 2];\nxs[2] = 3;");
+
+        assert_eq!(err.message, "List index 2 out of range for length 2.");
+        assert_eq!(err.token.lexeme, "]");
+        assert_eq!(err.token.line, 2);
+    }
+
+    #[test]
+    fn error_list_index_not_integer() {
+        let err = run_err("[1, 2][0.5];");
+
+        assert_eq!(err.message, "List index must be an integer.");
+    }
+
+    #[test]
+    fn map_literals_lookup_and_natives() {
+        let environment = run_ok(
+            r#"
+            var person = {"name": "izanami", "age": 1, 0: "zero", nil: true};
+            person["age"] = person["age"] + 1;
+            person[true] = "added";
+            var removed = remove(person, "name");
+            var missing = remove(person, "name");
+
+            var age = person["age"];
+            var negative_zero = person[-0];
+            var person_keys = keys(person);
+            var map_values = values({"a": 1, "b": 2});
+            var has_age = has(person, "age");
+            var has_name = has(person, "name");
+            var size = len(person);
+            var empty = len({});
+            var same = {"a": 1, "b": [2]} == {"b": [2], "a": 1};"#,
+        );
+
+        let list = |values: Vec<LiteralType>| LiteralType::List(Rc::new(RefCell::new(values)));
+        assert_global(
+            &environment,
+            "removed",
+            LiteralType::string_literal("izanami"),
+        );
+        assert_global(&environment, "missing", LiteralType::Nil);
+        assert_global(&environment, "age", LiteralType::number_literal(2.0));
+        assert_global(
+            &environment,
+            "negative_zero",
+            LiteralType::string_literal("zero"),
+        );
+        assert_global(
+            &environment,
+            "person_keys",
+            list(vec![
+                LiteralType::string_literal("age"),
+                LiteralType::number_literal(0.0),
+                LiteralType::Nil,
+                LiteralType::Bool(true),
+            ]),
+        );
+        assert_global(
+            &environment,
+            "map_values",
+            list(vec![
+                LiteralType::number_literal(1.0),
+                LiteralType::number_literal(2.0),
+            ]),
+        );
+        assert_global(&environment, "has_age", LiteralType::Bool(true));
+        assert_global(&environment, "has_name", LiteralType::Bool(false));
+        assert_global(&environment, "size", LiteralType::number_literal(4.0));
+        assert_global(&environment, "empty", LiteralType::number_literal(0.0));
+        assert_global(&environment, "same", LiteralType::Bool(true));
+    }
+
+    #[test]
+    fn error_unhashable_map_key() {
+        let err = run_err("var m = {}; m[[1]] = 2;");
+
+        assert_eq!(
+            err.message,
+            "Map keys must be strings, numbers, booleans or nil."
+        );
+    }
+
+    #[test]
+    fn error_missing_map_key() {
+        let err = run_err(r#"var m = {"a": 1}; m["b"];"#);
+
+        assert_eq!(err.message, "Key b not found in map.");
+    }
+
+    #[test]
+    fn string_interpolation() {
+        let environment = run_ok(
+            r#"
+            var name = "izanami";
+            var age = 1;
+            var greeting = "hello ${name}, you are ${age + 1}${"!"}";
+            var nested = "${"a${1 == 1}b"} ${[1, 2]}";
+            var plain = "costs $5 {not interpolated}";"#,
+        );
+
+        assert_global(
+            &environment,
+            "greeting",
+            LiteralType::string_literal("hello izanami, you are 2!"),
+        );
+        assert_global(
+            &environment,
+            "nested",
+            LiteralType::string_literal("atrueb [1, 2]"),
+        );
+        assert_global(
+            &environment,
+            "plain",
+            LiteralType::string_literal("costs $5 {not interpolated}"),
+        );
+    }
+
+    #[test]
+    fn modulo_power_and_floor_division() {
+        let environment = run_ok(
+            r#"
+            var modulo = 7 % 3;
+            var negative_modulo = -7 % 3;
+            var floor_division = 7 ~/ 2;
+            var negative_floor_division = -7 ~/ 2;
+            var identity = (-7 ~/ 2) * 2 + -7 % 2;
+            var right_associative = 2 ** 3 ** 2;
+            var tighter_than_minus = -2 ** 2;
+            var negative_exponent = 2 ** -1;
+            var precedence = 1 + 2 * 3 ** 2 % 5;"#,
+        );
+
+        assert_global(&environment, "modulo", LiteralType::number_literal(1.0));
+        assert_global(
+            &environment,
+            "negative_modulo",
+            LiteralType::number_literal(2.0),
+        );
+        assert_global(
+            &environment,
+            "floor_division",
+            LiteralType::number_literal(3.0),
+        );
+        assert_global(
+            &environment,
+            "negative_floor_division",
+            LiteralType::number_literal(-4.0),
+        );
+        assert_global(&environment, "identity", LiteralType::number_literal(-7.0));
+        assert_global(
+            &environment,
+            "right_associative",
+            LiteralType::number_literal(512.0),
+        );
+        assert_global(
+            &environment,
+            "tighter_than_minus",
+            LiteralType::number_literal(-4.0),
+        );
+        assert_global(
+            &environment,
+            "negative_exponent",
+            LiteralType::number_literal(0.5),
+        );
+        assert_global(&environment, "precedence", LiteralType::number_literal(4.0));
+    }
+
+    #[test]
+    fn error_modulo_operands() {
+        let err = run_err(r#""a" % 2;"#);
+
+        assert_eq!(err.message, "Operands must be numbers");
+        assert_eq!(err.token.lexeme, "%");
+    }
+
+    #[test]
+    fn bitwise_and_shift_operators() {
+        let environment = run_ok(
+            r#"
+            var bit_and = 12 & 10;
+            var bit_or = 12 | 10;
+            var bit_xor = 12 ^ 10;
+            var bit_not = ~5;
+            var left_shift = 1 << 10;
+            var right_shift = -16 >> 2;
+            var precedence = 1 | 2 << 1 + 1 == 9;
+            var flags = 0;
+            flags = flags | 1 << 3;
+            var has_flag = (flags & 8) != 0;"#,
+        );
+
+        assert_global(&environment, "bit_and", LiteralType::number_literal(8.0));
+        assert_global(&environment, "bit_or", LiteralType::number_literal(14.0));
+        assert_global(&environment, "bit_xor", LiteralType::number_literal(6.0));
+        assert_global(&environment, "bit_not", LiteralType::number_literal(-6.0));
+        assert_global(
+            &environment,
+            "left_shift",
+            LiteralType::number_literal(1024.0),
+        );
+        assert_global(
+            &environment,
+            "right_shift",
+            LiteralType::number_literal(-4.0),
+        );
+        assert_global(&environment, "precedence", LiteralType::Bool(true));
+        assert_global(&environment, "has_flag", LiteralType::Bool(true));
+    }
+
+    #[test]
+    fn error_bitwise_fractional_operand() {
+        let err = run_err("1.5 & 1;");
+
+        assert_eq!(err.message, "Operands must be integers");
+    }
+
+    #[test]
+    fn error_bitwise_out_of_range_operand() {
+        let err = run_err("~(2.0 ** 63);");
+
+        assert_eq!(err.message, "Operand must be an integer");
+    }
+
+    #[test]
+    fn error_shift_amount() {
+        let err = run_err("1 << 64;");
+
+        assert_eq!(err.message, "Shift amount must be between 0 and 63");
+    }
+
+    #[test]
+    fn error_negate_non_number() {
+        let err = run_err(r#"-"a";"#);
+
+        assert_eq!(err.message, "Operand must be a number");
+    }
+
+    #[test]
+    fn compound_assignment_and_increments() {
+        let environment = run_ok(
+            r#"
+            var x = 10;
+            x += 5;
+            x -= 3;
+            x *= 2;
+            x /= 4;
+
+            var i = 0;
+            var post = i++;
+            var pre = ++i;
+            var post_decrement = i--;
+
+            class Point { init() { this.x = 1; } }
+            var calls = 0;
+            var p = Point();
+            fun point() { calls++; return p; }
+            point().x += 10;
+            point().x++;
+
+            var xs = [1, 2];
+            var index_calls = 0;
+            fun index() { index_calls += 1; return 1; }
+            xs[index()] *= 10;
+            --xs[0];
+
+            var m = {"count": 0};
+            m["count"]++;
+            var s = "a";
+            s += "b";"#,
+        );
+
+        assert_global(&environment, "x", LiteralType::number_literal(6.0));
+        assert_global(&environment, "post", LiteralType::number_literal(0.0));
+        assert_global(&environment, "pre", LiteralType::number_literal(2.0));
+        assert_global(
+            &environment,
+            "post_decrement",
+            LiteralType::number_literal(2.0),
+        );
+        assert_global(&environment, "i", LiteralType::number_literal(1.0));
+        assert_global(&environment, "calls", LiteralType::number_literal(2.0));
+        assert_global(
+            &environment,
+            "index_calls",
+            LiteralType::number_literal(1.0),
+        );
+
+        let p = global(&environment, "p");
+        let LiteralType::Instance(p) = p else {
+            panic!("p should be an instance");
+        };
+        let x = Instance::get(&p, &Token::new(TokenType::Identifier, "x", None, 0));
+        assert!(is_equal(&x.unwrap(), &LiteralType::number_literal(12.0)));
+
+        let list = |values: Vec<LiteralType>| LiteralType::List(Rc::new(RefCell::new(values)));
+        assert_global(
+            &environment,
+            "xs",
+            list(vec![
+                LiteralType::number_literal(0.0),
+                LiteralType::number_literal(20.0),
+            ]),
+        );
+        assert_global(&environment, "s", LiteralType::string_literal("ab"));
+    }
+
+    #[test]
+    fn error_compound_assignment_types() {
+        let err = run_err(r#"var b = true; b += 1;"#);
+
+        assert_eq!(err.message, "Operands must be two numbers or two strings");
+        assert_eq!(err.token.lexeme, "+=");
+    }
+
+    #[test]
+    fn error_undefined_variable() {
+        let err = run_err("print nope;");
+
+        assert_eq!(err.message, "Undefined variable nope.");
+    }
+
+    #[test]
+    fn int_arithmetic_stays_int() {
+        let environment = run_ok(
+            r#"
+            var sum = 1 + 2 * 3;
+            var quot = 7 ~/ -2;
+            var rem = 7 % -2;
+            var pow = 2 ** 10;
+            var half = 7 / 2;
+            var inverse = 2 ** -1;
+            var mixed = 1 + 0.5;
+            var shown = "${sum} ${len([1, 2])} ${1.0}";"#,
+        );
+
+        assert!(matches!(global(&environment, "sum"), LiteralType::Int(7)));
+        assert!(matches!(global(&environment, "quot"), LiteralType::Int(-4)));
+        assert!(matches!(global(&environment, "rem"), LiteralType::Int(-1)));
+        assert!(matches!(
+            global(&environment, "pow"),
+            LiteralType::Int(1024)
+        ));
+        assert!(matches!(global(&environment, "half"), LiteralType::Number(n) if n == 3.5));
+        assert!(matches!(global(&environment, "inverse"), LiteralType::Number(n) if n == 0.5));
+        assert!(matches!(global(&environment, "mixed"), LiteralType::Number(n) if n == 1.5));
+        assert_global(
+            &environment,
+            "shown",
+            LiteralType::string_literal("7 2 1.00"),
+        );
+    }
+
+    #[test]
+    fn int_and_float_compare_equal() {
+        let environment = run_ok(
+            r#"
+            var same = 1 == 1.0;
+            var different = 1 == 1.5;
+            var m = {1: "one"};
+            var by_float = m[1.0];
+            var listed = [1, 2] == [1.0, 2.0];"#,
+        );
+
+        assert_global(&environment, "same", LiteralType::Bool(true));
+        assert_global(&environment, "different", LiteralType::Bool(false));
+        assert_global(&environment, "by_float", LiteralType::string_literal("one"));
+        assert_global(&environment, "listed", LiteralType::Bool(true));
+    }
+
+    #[test]
+    fn int_and_float_compare_exactly() {
+        let environment = run_ok(
+            r#"
+            var above = 9007199254740993 > 9007199254740992.0;
+            var below = 9007199254740992.0 < 9007199254740993;
+            var not_equal = 9007199254740993 <= 9007199254740992.0;
+            var fraction = 2 < 2.5 and -2 > -2.5 and 2 >= 2.0 and -2 >= -2.0;
+            var huge = 9223372036854775807 < 9223372036854775808.0;
+            var nan = 1 < 0.0 / 0.0 or 1 >= 0.0 / 0.0;"#,
+        );
+
+        assert_global(&environment, "above", LiteralType::Bool(true));
+        assert_global(&environment, "below", LiteralType::Bool(true));
+        assert_global(&environment, "not_equal", LiteralType::Bool(false));
+        assert_global(&environment, "fraction", LiteralType::Bool(true));
+        assert_global(&environment, "huge", LiteralType::Bool(true));
+        assert_global(&environment, "nan", LiteralType::Bool(false));
+    }
+
+    #[test]
+    fn error_int_overflow() {
+        let err = run_err("9223372036854775807 + 1;");
+
+        assert_eq!(err.message, "Integer overflow");
+    }
+
+    #[test]
+    fn int_min_remainder() {
+        let environment = run_ok("var min = -9223372036854775807 - 1; var rem = min % -1;");
+
+        assert_global(&environment, "rem", LiteralType::Int(0));
+    }
+
+    #[test]
+    fn error_int_division_by_zero() {
+        let err = run_err("1 % 0;");
+
+        assert_eq!(err.message, "Division by zero");
+    }
+
+    #[test]
+    fn throw_and_catch() {
+        let environment = run_ok(
+            r#"
+            var caught;
+            var log = [];
+            fun fail(x) {
+                push(log, "before");
+                throw x * 2;
+                push(log, "after");
+            }
+            try {
+                fail(21);
+            } catch (e) {
+                caught = e;
+            } finally {
+                push(log, "finally");
+            }"#,
+        );
+
+        assert_global(&environment, "caught", LiteralType::Int(42));
+        assert_global(
+            &environment,
+            "log",
+            LiteralType::List(Rc::new(RefCell::new(vec![
+                LiteralType::string_literal("before"),
+                LiteralType::string_literal("finally"),
+            ]))),
+        );
+    }
+
+    #[test]
+    fn catch_runtime_error_as_value() {
+        let environment = run_ok(
+            r#"
+            var message;
+            var line;
+            var arity;
+            try {
+                1 + nil;
+            } catch (e) {
+                message = e.message;
+                line = e.line;
+            }
+            try { len(); } catch (e) { arity = e.message; }"#,
+        );
+
+        assert_global(
+            &environment,
+            "message",
+            LiteralType::string_literal("Operands must be two numbers or two strings"),
+        );
+        assert_global(&environment, "line", LiteralType::Int(6));
+        assert_global(
+            &environment,
+            "arity",
+            LiteralType::string_literal("Expected 1 arguments but got 0."),
+        );
+    }
+
+    #[test]
+    fn finally_runs_on_return_and_rethrow() {
+        let environment = run_ok(
+            r#"
+            var cleaned = 0;
+            fun f() {
+                try {
+                    return 1;
+                } finally {
+                    cleaned = cleaned + 1;
+                }
+            }
+            var result = f();
+            var outer;
+            try {
+                try {
+                    throw "inner";
+                } finally {
+                    cleaned = cleaned + 1;
+                }
+            } catch (e) {
+                outer = e;
+            }"#,
+        );
+
+        assert_global(&environment, "result", LiteralType::Int(1));
+        assert_global(&environment, "cleaned", LiteralType::Int(2));
+        assert_global(&environment, "outer", LiteralType::string_literal("inner"));
+    }
+
+    #[test]
+    fn error_uncaught_throw() {
+        let err = run_err("\nthrow \"oops\";");
+
+        assert_eq!(err.message, "Uncaught exception: oops");
+        assert_eq!(err.token.line, 2);
+    }
+
+    // the directory is removed once the test is done with it, even when the test fails
+    struct ModuleDir(std::path::PathBuf);
+
+    impl std::fmt::Display for ModuleDir {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.0.display())
+        }
+    }
+
+    impl Drop for ModuleDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // writes the files into a fresh directory, so imports can be given as absolute paths
+    fn module_dir(name: &str, files: &[(&str, &str)]) -> ModuleDir {
+        let dir = std::env::temp_dir().join(format!("izanami_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (file, src) in files {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, src).unwrap();
+        }
+        ModuleDir(dir)
+    }
+
+    #[test]
+    fn import_module_namespace_and_names() {
+        let dir = module_dir(
+            "imports",
+            &[
+                (
+                    "math.iz",
+                    r#"
+                    import double from "lib/helpers";
+                    var runs = 0;
+                    runs = runs + 1;
+                    fun quadruple(x) { return double(double(x)); }"#,
+                ),
+                ("lib/helpers.iz", "fun double(x) { return x * 2; }"),
+            ],
+        );
+        let environment = run_ok(&format!(
+            r#"
+            import "{dir}/math";
+            import quadruple, runs from "{dir}/math.iz";
+            var namespaced = math.quadruple(2);
+            var imported = quadruple(3);
+            var shown = "${{math}}";"#
+        ));
+
+        assert_global(&environment, "namespaced", LiteralType::Int(8));
+        assert_global(&environment, "imported", LiteralType::Int(12));
+        // both imports share the cached module, so it only ran once
+        assert_global(&environment, "runs", LiteralType::Int(1));
+        assert_global(
+            &environment,
+            "shown",
+            LiteralType::string_literal("<module math>"),
+        );
+    }
+
+    #[test]
+    fn error_import_missing_module() {
+        let err = run_err("\nimport \"does/not/exist\";");
+
+        assert_eq!(err.message, "Module 'does/not/exist' not found.");
+        assert_eq!(err.token.line, 2);
+    }
+
+    #[test]
+    fn error_import_cycle() {
+        let dir = module_dir(
+            "cycle",
+            &[("a.iz", "import \"b\";"), ("b.iz", "\n\nimport \"a\";")],
+        );
+        let err = run_err(&format!("import \"{dir}/a\";"));
+
+        assert_eq!(err.message, "Import cycle detected while importing 'a'.");
+        assert_eq!(err.token.line, 3);
+    }
+
+    #[test]
+    fn error_import_unknown_name() {
+        let dir = module_dir("unknown", &[("m.iz", "var a = 1;")]);
+        let err = run_err(&format!("import b from \"{dir}/m\";"));
+
+        assert_eq!(err.message, "Module m has no binding b.");
+    }
+
+    #[test]
+    fn error_import_native() {
+        let dir = module_dir("natives", &[("m.iz", "fun len(x) { return 0; }")]);
+        let err = run_err(&format!("import push from \"{dir}/m\";"));
+
+        assert_eq!(err.message, "Module m has no binding push.");
+
+        let err = run_err(&format!("import \"{dir}/m\";\nvar p = m.push;"));
+
+        assert_eq!(err.message, "Module m has no binding push.");
+
+        // a native the module redefined is its own
+        let environment = run_ok(&format!("import len from \"{dir}/m\";\nvar l = len([1]);"));
+
+        assert_global(&environment, "l", LiteralType::Int(0));
+    }
+
+    #[test]
+    fn match_literal_binding_and_guard_patterns() {
+        let environment = run_ok(
+            r#"
+            fun describe(v) {
+                return match (v) {
+                    1 => "one",
+                    "a" | "b" => "letter",
+                    -2.5 => "negative",
+                    nil => "nothing",
+                    x if x > 10 => "big ${x}",
+                    _ => "other",
+                };
+            }
+            var results = [describe(1.0), describe("b"), describe(-2.5), describe(11), describe(nil), describe(5)];
+            var log = [];
+            match (3) {
+                3 => push(log, "three"),
+                _ => { push(log, "never"); }
+            }"#,
+        );
+
+        assert_global(
+            &environment,
+            "results",
+            LiteralType::List(Rc::new(RefCell::new(vec![
+                LiteralType::string_literal("one"),
+                LiteralType::string_literal("letter"),
+                LiteralType::string_literal("negative"),
+                LiteralType::string_literal("big 11"),
+                LiteralType::string_literal("nothing"),
+                LiteralType::string_literal("other"),
+            ]))),
+        );
+        assert_global(
+            &environment,
+            "log",
+            LiteralType::List(Rc::new(RefCell::new(vec![LiteralType::string_literal(
+                "three",
+            )]))),
+        );
+    }
+
+    #[test]
+    fn match_list_and_map_patterns() {
+        let environment = run_ok(
+            r#"
+            fun sum(xs) {
+                return match (xs) {
+                    [] => 0,
+                    [head, ...tail] => head + sum(tail),
+                };
+            }
+            var total = sum([1, 2, 3]);
+            var pair = match ([1, [2, 3]]) {
+                [a, [b, _]] => a + b,
+                _ => nil,
+            };
+            var person = {"name": "izanami", "age": 3, "role": "admin"};
+            var greeting = match (person) {
+                {"role": "guest"} => "hi guest",
+                {name, "age": age} if age > 2 => "hi ${name}",
+                _ => "hi",
+            };"#,
+        );
+
+        assert_global(&environment, "total", LiteralType::Int(6));
+        assert_global(&environment, "pair", LiteralType::Int(3));
+        assert_global(
+            &environment,
+            "greeting",
+            LiteralType::string_literal("hi izanami"),
+        );
+    }
+
+    #[test]
+    fn error_no_match_arm() {
+        let err = run_err("match (3) { 1 => 1, [x] => x, }");
+
+        assert_eq!(err.message, "No match arm matches 3.");
+    }
+
+    #[test]
+    fn default_and_rest_params() {
+        let environment = run_ok(
+            r#"
+            var base = 100;
+            fun f(a, b = a + base, ...rest) { return [a, b, rest]; }
+            var only_required = f(1);
+            var all = f(1, 2, 3, 4);
+            base = 0;
+            var evaluated_per_call = f(1);
+            var arrow = fun (x = 2) => x * x;
+            var squared = arrow();"#,
+        );
+
+        let list = |values: Vec<LiteralType>| LiteralType::List(Rc::new(RefCell::new(values)));
+        assert_global(
+            &environment,
+            "only_required",
+            list(vec![
+                LiteralType::Int(1),
+                LiteralType::Int(101),
+                list(vec![]),
+            ]),
+        );
+        assert_global(
+            &environment,
+            "all",
+            list(vec![
+                LiteralType::Int(1),
+                LiteralType::Int(2),
+                list(vec![LiteralType::Int(3), LiteralType::Int(4)]),
+            ]),
+        );
+        assert_global(
+            &environment,
+            "evaluated_per_call",
+            list(vec![LiteralType::Int(1), LiteralType::Int(1), list(vec![])]),
+        );
+        assert_global(&environment, "squared", LiteralType::Int(4));
+    }
+
+    #[test]
+    fn error_arity_with_defaults_and_rest() {
+        let range = run_err("fun f(a, b = 1) {} f(1, 2, 3);");
+        let at_least = run_err("fun f(a, ...rest) {} f();");
+
+        assert_eq!(range.message, "Expected 1 to 2 arguments but got 3.");
+        assert_eq!(at_least.message, "Expected at least 1 arguments but got 0.");
+    }
+
+    #[test]
+    fn named_arguments() {
+        let environment = run_ok(
+            r#"
+            fun f(a, b = 2, c = 3) { return [a, b, c]; }
+            var mixed = f(1, c: 30);
+            var reordered = f(c: 3, b: 2, a: 1);
+            class Point {
+                init(x, y = 0) {
+                    this.x = x;
+                    this.y = y;
+                }
+            }
+            var point = Point(y: 5, x: 4);
+            var coordinates = [point.x, point.y];"#,
+        );
+
+        let list = |values: Vec<i64>| {
+            let values = values.into_iter().map(LiteralType::Int).collect();
+            LiteralType::List(Rc::new(RefCell::new(values)))
+        };
+        assert_global(&environment, "mixed", list(vec![1, 2, 30]));
+        assert_global(&environment, "reordered", list(vec![1, 2, 3]));
+        assert_global(&environment, "coordinates", list(vec![4, 5]));
+    }
+
+    #[test]
+    fn error_named_arguments() {
+        let unknown = run_err("fun f(a) {} f(b: 1);");
+        let duplicate = run_err("fun f(a) {} f(1, a: 2);");
+        let missing = run_err("fun f(a, b) {} \nf(b: 2);");
+        let native = run_err("len(x: 1);");
+
+        assert_eq!(unknown.message, "Unknown parameter b.");
+        assert_eq!(duplicate.message, "Parameter a got more than one argument.");
+        assert_eq!(missing.message, "Missing argument for parameter a.");
+        assert_eq!(missing.token.line, 2);
+        assert_eq!(
+            native.message,
+            "Native function len doesn't take named arguments."
+        );
+    }
+
+    #[test]
+    fn destructuring_declarations_and_assignment() {
+        let environment = run_ok(
+            r#"
+            var [a, b, ...rest] = [1, 2, 3, 4];
+            var {name, "age": age} = {"name": "izanami", "age": 3};
+            class Person {
+                init(name) {
+                    this.name = name;
+                }
+            }
+            fun swap() {
+                var x = 1;
+                var y = 2;
+                [x, y] = [y, x];
+                return [x, y];
+            }
+            var swapped = swap();
+            var {"name": field} = Person("kami");"#,
+        );
+
+        let list = |values: Vec<i64>| {
+            let values = values.into_iter().map(LiteralType::Int).collect();
+            LiteralType::List(Rc::new(RefCell::new(values)))
+        };
+        assert_global(&environment, "a", LiteralType::Int(1));
+        assert_global(&environment, "b", LiteralType::Int(2));
+        assert_global(&environment, "rest", list(vec![3, 4]));
+        assert_global(&environment, "name", LiteralType::string_literal("izanami"));
+        assert_global(&environment, "age", LiteralType::Int(3));
+        assert_global(&environment, "swapped", list(vec![2, 1]));
+        assert_global(&environment, "field", LiteralType::string_literal("kami"));
+    }
+
+    #[test]
+    fn error_destructuring_shape() {
+        let short = run_err("var [a, b] = [1];");
+        let not_list = run_err("var [a] = 1;");
+        let missing_key = run_err("var {name} = {};");
+        let assign = run_err("var a; var b; [a, b] = [1, 2, 3];");
+
+        assert_eq!(
+            short.message,
+            "Can't destructure: Expected 2 elements but got 1."
+        );
+        assert_eq!(
+            not_list.message,
+            "Can't destructure: Expected a list but got 1."
+        );
+        assert_eq!(
+            missing_key.message,
+            "Can't destructure: Key name not found."
+        );
+        assert_eq!(
+            assign.message,
+            "Can't destructure: Expected 2 elements but got 3."
+        );
+    }
+
+    #[test]
+    fn constants() {
+        let environment = run_ok("const x = 1; var y = x + 1; { const x = 3; y = y + x; }");
+
+        assert_global(&environment, "x", LiteralType::Int(1));
+        assert_global(&environment, "y", LiteralType::Int(5));
+    }
+
+    #[test]
+    fn error_assign_constant_at_runtime() {
+        // f is resolved before the constant exists, so only the runtime catches it
+        let err = run_err("fun f() { x = 2; } const x = 1; f();");
+
+        assert_eq!(err.message, "Cannot assign to constant x.");
+    }
+
+    #[test]
+    fn generators() {
+        let environment = run_ok(
+            r#"
+            fun range(n) { var i = 0; while (i < n) { yield i; i = i + 1; } }
+            var g = range(2);
+            var before = g.done;
+            var a = g.next();
+            var b = g.next();
+            var c = g.next();
+            var after = g.done;
+            var again = g.next();
+            "#,
+        );
+
+        assert_global(&environment, "before", LiteralType::Bool(false));
+        assert_global(&environment, "a", LiteralType::Int(0));
+        assert_global(&environment, "b", LiteralType::Int(1));
+        assert_global(&environment, "c", LiteralType::Nil);
+        assert_global(&environment, "after", LiteralType::Bool(true));
+        assert_global(&environment, "again", LiteralType::Nil);
+    }
+
+    #[test]
+    fn generators_resume_inside_statements() {
+        let environment = run_ok(
+            r#"
+            fun steps() {
+                for (var i = 0; i < 3; i++) {
+                    if (i == 1) continue;
+                    try { yield i; throw "oops"; } catch (e) { yield e; } finally { yield "finally"; }
+                }
+                match (1) { 1 => { var x = "arm"; yield x; yield x + "!"; } }
+                return "end";
+            }
+            var g = steps();
+            var values = [];
+            while (!g.done) push(values, g.next());
+            "#,
+        );
+
+        assert_eq!(
+            global(&environment, "values").to_string(),
+            "[0, oops, finally, 2, oops, finally, arm, arm!, end]"
+        );
+    }
+
+    #[test]
+    fn error_generator_already_running() {
+        let err = run_err("fun f() { yield g.next(); } var g = f(); g.next();");
+
+        assert_eq!(err.message, "Generator is already running.");
+    }
+
+    #[test]
+    fn for_in_loops() {
+        let environment = run_ok(
+            r#"
+            var seen = [];
+            for (x in [1, 2, 3, 4]) { if (x == 2) continue; if (x == 4) break; push(seen, x); }
+            for (key in {"a": 1, "b": 2}) push(seen, key);
+            for (c in "hi") push(seen, c);
+            fun two() { yield "g1"; yield "g2"; return "not visited"; }
+            for (v in two()) push(seen, v);
+            class Countdown {
+                init(n) { this.n = n; }
+                iter() { return this; }
+                next() { if (this.n == 0) return nil; this.n = this.n - 1; return this.n; }
+            }
+            for (n in Countdown(2)) push(seen, n);
+            class Pair { iter() { yield "p1"; yield "p2"; } }
+            for (p in Pair()) push(seen, p);
+            var closures = [];
+            for (x in [1, 2]) push(closures, fun() { return x; });
+            var captured = closures[0]() + closures[1]();
+            "#,
+        );
+
+        assert_eq!(
+            global(&environment, "seen").to_string(),
+            "[1, 3, a, b, h, i, g1, g2, 1, 0, p1, p2]"
+        );
+        assert_global(&environment, "captured", LiteralType::Int(3));
+    }
+
+    #[test]
+    fn for_in_inside_generator() {
+        let environment = run_ok(
+            r#"
+            fun pairs(xs) { for (x in xs) { yield x; yield x * 10; } }
+            var seen = [];
+            for (v in pairs([1, 2])) push(seen, v);
+            "#,
+        );
+
+        assert_eq!(global(&environment, "seen").to_string(), "[1, 10, 2, 20]");
+    }
+
+    #[test]
+    fn error_for_in_not_iterable() {
+        let number = run_err("for (x in 1) print x;");
+        let iter = run_err("class A { iter() { return 1; } } for (x in A()) print x;");
+
+        assert_eq!(number.message, "Can't iterate over 1.");
+        assert_eq!(
+            iter.message,
+            "iter() must return a generator or an instance but got 1."
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        let environment = run_ok(
+            r#"
+            var seen = [];
+            for (i in 0..3) push(seen, i);
+            for (i in (10..=0).step(-5)) push(seen, i);
+            var lengths = [len(0..10), len(0..=10), len(5..0), len((0..10).step(3))];
+            var members = [2 in 0..3, 3 in 0..3, 9 in (0..10).step(3), 1.5 in 0..3];
+            var slices = ["hello"[1..3], "héllo"[0..=1], "abc"[3..3]];
+            var shown = "${1..=2}";
+            "#,
+        );
+
+        assert_eq!(
+            global(&environment, "seen").to_string(),
+            "[0, 1, 2, 10, 5, 0]"
+        );
+        assert_eq!(
+            global(&environment, "lengths").to_string(),
+            "[10, 11, 0, 4]"
+        );
+        assert_eq!(
+            global(&environment, "members").to_string(),
+            "[true, false, true, false]"
+        );
+        assert_eq!(global(&environment, "slices").to_string(), "[el, hé, ]");
+        assert_global(&environment, "shown", LiteralType::string_literal("1..=2"));
+    }
+
+    #[test]
+    fn in_operator() {
+        let environment = run_ok(
+            r#"
+            var found = [2 in [1, 2], 3 in [1, 2], "a" in {"a": 1}, "ell" in "hello"];
+            "#,
+        );
+
+        assert_eq!(
+            global(&environment, "found").to_string(),
+            "[true, false, true, true]"
+        );
+    }
+
+    #[test]
+    fn error_ranges() {
+        let bounds = run_err("var r = 1.5..2;");
+        let step = run_err("var r = (0..2).step(0);");
+        let slice = run_err(r#"var s = "abc"[1..5];"#);
+
+        assert_eq!(bounds.message, "Range bounds must be integers.");
+        assert_eq!(step.message, "Range step must be a non-zero integer.");
+        assert_eq!(slice.message, "Can't slice a string of length 3 with 1..5.");
+    }
+
+    #[test]
+    fn nil_coalescing() {
+        let environment = run_ok(
+            r#"
+            var calls = 0;
+            fun fallback() { calls = calls + 1; return "fallback"; }
+            var missing = nil ?? "default";
+            var kept = false ?? "default";
+            var chained = nil ?? nil ?? 3;
+            var skipped = 1 ?? fallback();
+            var ternary = nil ?? true ? "yes" : "no";
+            "#,
+        );
+
+        assert_global(
+            &environment,
+            "missing",
+            LiteralType::string_literal("default"),
+        );
+        assert_global(&environment, "kept", LiteralType::Bool(false));
+        assert_global(&environment, "chained", LiteralType::Int(3));
+        assert_global(&environment, "skipped", LiteralType::Int(1));
+        assert_global(&environment, "calls", LiteralType::Int(0));
+        assert_global(&environment, "ternary", LiteralType::string_literal("yes"));
+    }
+
+    #[test]
+    fn optional_chaining() {
+        let environment = run_ok(
+            r#"
+            class Node { init(value) { this.value = value; this.next = nil; } get() { return this.value; } }
+            var node = Node(1);
+            var none = nil;
+            var field = node?.value;
+            var nil_field = none?.value;
+            var nil_method = none?.get();
+            var method = node?.get();
+            var rest_skipped = node.next?.next.value;
+            var f = fun(x) { return x * 2; };
+            var called = f?.(4);
+            var nil_called = none?.(4);
+            var defaulted = none?.value ?? "default";
+            "#,
+        );
+
+        assert_global(&environment, "field", LiteralType::Int(1));
+        assert_global(&environment, "nil_field", LiteralType::Nil);
+        assert_global(&environment, "nil_method", LiteralType::Nil);
+        assert_global(&environment, "method", LiteralType::Int(1));
+        assert_global(&environment, "rest_skipped", LiteralType::Nil);
+        assert_global(&environment, "called", LiteralType::Int(8));
+        assert_global(&environment, "nil_called", LiteralType::Nil);
+        assert_global(
+            &environment,
+            "defaulted",
+            LiteralType::string_literal("default"),
+        );
+    }
+
+    #[test]
+    fn error_optional_chain_only_guards_nil() {
+        let err = run_err("var n = 1; var x = n?.field;");
+
+        assert_eq!(err.message, "Only instances have properties.");
+    }
+
+    #[test]
+    fn self_containing_list() {
+        let environment = run_ok(
+            r#"
+            var a = [1];
+            push(a, a);
+            var same = a == a;
+            var shown = "${a}";
+            "#,
+        );
+
+        assert_global(&environment, "same", LiteralType::Bool(true));
+        assert_global(
+            &environment,
+            "shown",
+            LiteralType::string_literal("[1, [...]]"),
+        );
+    }
+
+    #[test]
+    fn self_containing_map() {
+        let environment = run_ok(
+            r#"
+            var m = {"a": 1};
+            m["self"] = m;
+            var same = m == m;
+            var shown = "${m}";
+            "#,
+        );
+
+        assert_global(&environment, "same", LiteralType::Bool(true));
+        assert_global(
+            &environment,
+            "shown",
+            LiteralType::string_literal("{a: 1, self: {...}}"),
+        );
+    }
+
+    #[test]
+    fn native_error_in_for_in_is_caught() {
+        let environment = run_ok(
+            r#"
+            class Cursor { init(next) { this.next = next; } }
+            class Items {
+                init(source) { this.source = source; }
+                iter() { return Cursor(this.source.next); }
+            }
+            fun walk() {
+                try {
+                    for (x in Items(g)) {}
+                } catch (e) {
+                    yield e;
+                }
+            }
+            var g = walk();
+            // the cursor's next is the running generator's own, calling it raises
+            var error = g.next();
+            var message = error.message;
+            var line = error.line;
+            "#,
+        );
+
+        assert_global(
+            &environment,
+            "message",
+            LiteralType::string_literal("Generator is already running."),
+        );
+        assert_global(&environment, "line", LiteralType::Int(9));
+    }
+
+    #[test]
+    fn distinct_self_containing_lists() {
+        let environment = run_ok(
+            r#"
+            var a = [1];
+            push(a, a);
+            var b = [1];
+            push(b, b);
+            var c = [2];
+            push(c, c);
+            var same = a == b;
+            var different = a == c;
+            "#,
+        );
+
+        assert_global(&environment, "same", LiteralType::Bool(true));
+        assert_global(&environment, "different", LiteralType::Bool(false));
+    }
+
+    #[test]
+    fn distinct_self_containing_maps() {
+        let environment = run_ok(
+            r#"
+            var a = {};
+            a["s"] = a;
+            var b = {};
+            b["s"] = b;
+            var c = {};
+            c["s"] = c;
+            c["t"] = 1;
+            var same = a == b;
+            var different = a == c;
+            "#,
+        );
+
+        assert_global(&environment, "same", LiteralType::Bool(true));
+        assert_global(&environment, "different", LiteralType::Bool(false));
+    }
+}
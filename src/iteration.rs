@@ -0,0 +1,144 @@
+use std::{cell::RefCell, rc::Rc, vec};
+
+use crate::{
+    callable::CallableTrait,
+    class::Instance,
+    generator::{self, Generator},
+    interpreter::{InterpreterEnvironment, InterpreterSignal, RuntimeError},
+    range::Range,
+    token::{LiteralType, Token, TokenType},
+};
+
+// what a `for (x in iterable)` loop walks over
+#[derive(Debug)]
+pub enum Iteration {
+    // by index, so elements pushed during the loop are visited too
+    List {
+        list: Rc<RefCell<Vec<LiteralType>>>,
+        index: usize,
+    },
+    // ranges are never turned into a list, the elements are computed one at a time
+    Range {
+        range: Range,
+        index: u64,
+    },
+    // map keys and string characters, taken when the loop starts
+    Values(vec::IntoIter<LiteralType>),
+    // the value of a `return` ends the loop without being visited
+    Generator(Rc<RefCell<Generator>>),
+    // an instance with a `next` method, nil ends the loop
+    Instance(Rc<RefCell<Instance>>),
+}
+
+impl Iteration {
+    // the keyword is the `in`, errors point at it
+    pub fn new(
+        iterable: LiteralType,
+        keyword: &Token,
+        environment: &InterpreterEnvironment,
+    ) -> Result<Self, InterpreterSignal> {
+        match iterable {
+            LiteralType::List(list) => Ok(Iteration::List { list, index: 0 }),
+            LiteralType::Range(range) => Ok(Iteration::Range { range, index: 0 }),
+            LiteralType::Map(map) => {
+                let keys: Vec<LiteralType> =
+                    map.borrow().iter().map(|(k, _)| k.to_literal()).collect();
+                Ok(Iteration::Values(keys.into_iter()))
+            }
+            LiteralType::String(string) => {
+                let chars: Vec<LiteralType> = string
+                    .chars()
+                    .map(|c| LiteralType::String(c.to_string()))
+                    .collect();
+                Ok(Iteration::Values(chars.into_iter()))
+            }
+            LiteralType::Generator(generator) => Ok(Iteration::Generator(generator)),
+            // `iter()` hands out the iterator, usually `this` or a generator
+            LiteralType::Instance(instance) => {
+                match call_method(&instance, "iter", keyword, environment)? {
+                    LiteralType::Generator(generator) => Ok(Iteration::Generator(generator)),
+                    LiteralType::Instance(iterator) => Ok(Iteration::Instance(iterator)),
+                    other => Err(RuntimeError::new(
+                        keyword,
+                        format!("iter() must return a generator or an instance but got {other}."),
+                    ))?,
+                }
+            }
+            other => Err(RuntimeError::new(
+                keyword,
+                format!("Can't iterate over {other}."),
+            ))?,
+        }
+    }
+
+    // None once the iterable is exhausted
+    pub fn next(
+        &mut self,
+        keyword: &Token,
+        environment: &InterpreterEnvironment,
+    ) -> Result<Option<LiteralType>, InterpreterSignal> {
+        match self {
+            Iteration::List { list, index } => {
+                let value = list.borrow().get(*index).cloned();
+                *index += 1;
+                Ok(value)
+            }
+            Iteration::Range { range, index } => {
+                if *index >= range.len() {
+                    return Ok(None);
+                }
+                let value = range.nth(*index);
+                *index += 1;
+                Ok(Some(LiteralType::Int(value)))
+            }
+            Iteration::Values(values) => Ok(values.next()),
+            Iteration::Generator(generator) => {
+                let value = generator::resume(generator).map_err(|signal| match signal {
+                    InterpreterSignal::NativeRuntimeError { msg } => {
+                        RuntimeError::new(keyword, msg).into()
+                    }
+                    signal => signal,
+                })?;
+                Ok((!generator.borrow().is_done()).then_some(value))
+            }
+            Iteration::Instance(iterator) => {
+                match call_method(iterator, "next", keyword, environment)? {
+                    LiteralType::Nil => Ok(None),
+                    value => Ok(Some(value)),
+                }
+            }
+        }
+    }
+}
+
+fn call_method(
+    instance: &Rc<RefCell<Instance>>,
+    name: &str,
+    keyword: &Token,
+    environment: &InterpreterEnvironment,
+) -> Result<LiteralType, InterpreterSignal> {
+    let token = Token::new(TokenType::Identifier, name, None, keyword.line);
+    let LiteralType::Callable(method) = Instance::get(instance, &token)? else {
+        Err(RuntimeError::new(
+            keyword,
+            format!("Property {name} of an iterable must be a method."),
+        ))?
+    };
+
+    let arity = method.arity();
+    if !arity.accepts(0) {
+        Err(RuntimeError::new(
+            keyword,
+            format!("Expected {name}() to take 0 arguments but it takes {arity}."),
+        ))?
+    }
+
+    // a native method (like a generator's next) reports errors without a token, the call gets
+    // the `in` one, same as Expr::Call gives them its paren
+    method
+        .call(&[], environment)
+        .map_err(|signal| match signal {
+            InterpreterSignal::NativeRuntimeError { msg } => RuntimeError::new(keyword, msg).into(),
+            signal => signal,
+        })
+}
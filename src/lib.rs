@@ -3,21 +3,32 @@ use std::{
     error::Error,
     fs,
     io::{self, Write},
+    path::Path,
     rc::Rc,
 };
 
+use ast::Stmt;
 use environment::Environment;
 use interpreter::RuntimeError;
+use module::{Module, Modules};
 use parser::{ParseError, Parser};
+use resolver::{Locals, Resolver};
 use scanner::Scanner;
 use token::TokenType;
 
 mod ast;
 mod callable;
+mod class;
 mod environment;
+mod generator;
 mod interpreter;
+mod iteration;
+mod map;
+mod module;
 mod parser;
 mod printer;
+mod range;
+mod resolver;
 mod scanner;
 mod token;
 mod utils;
@@ -39,12 +50,42 @@ impl<E: Error + 'static> From<E> for RunError {
 pub fn run_file(path: &str) -> Result<(), RunError> {
     let file = fs::read_to_string(path).map_err(RunError::FileReadError)?;
     let environment = Rc::new(RefCell::new(Environment::new()));
+    let locals = Rc::new(RefCell::new(Locals::new()));
+    let modules = Rc::new(RefCell::new(Modules::new()));
+
+    // the script is registered like any other module, so its imports are relative to it and
+    // importing it back is a cycle
+    let path = Path::new(path)
+        .canonicalize()
+        .map_err(RunError::FileReadError)?;
+    modules
+        .borrow_mut()
+        .insert(Rc::new(Module::new(path.clone(), Rc::clone(&environment))));
+    modules.borrow_mut().start_loading(path);
+
+    run(&file, &environment, &locals, &modules)?;
+    Ok(())
+}
+
+pub fn run(
+    src: &str,
+    environment: &Rc<RefCell<Environment>>,
+    locals: &Rc<RefCell<Locals>>,
+    modules: &Rc<RefCell<Modules>>,
+) -> Result<(), RunError> {
+    let statements = parse(src, locals)?;
+
+    interpreter::interpret(&statements, environment, locals, modules)
+        .map_err(|x| x.into())
+        .inspect_err(runtime_error)
+        .map_err(RunError::RuntimeError)?;
 
-    run(&file, &environment)?;
     Ok(())
 }
 
-pub fn run(src: &str, environment: &Rc<RefCell<Environment>>) -> Result<(), RunError> {
+// scans, parses and resolves the source. Static errors are reported here, the resolved locals
+// are added to the shared table
+fn parse(src: &str, locals: &Rc<RefCell<Locals>>) -> Result<Vec<Stmt>, RunError> {
     let mut scanner = Scanner::new(src.to_string());
     let tokens = scanner.scan_tokens()?;
 
@@ -66,26 +107,34 @@ pub fn run(src: &str, environment: &Rc<RefCell<Environment>>) -> Result<(), RunE
         return Err(RunError::ParseError);
     }
 
-    let statements = statements.into_iter().flatten().collect();
+    let statements: Vec<Stmt> = statements.into_iter().flatten().collect();
 
-    interpreter::interpret(&statements, environment)
-        .map_err(|x| x.into())
-        .inspect_err(runtime_error)
-        .map_err(RunError::RuntimeError)?;
+    let resolved = Resolver::new().resolve(&statements).inspect_err(|errors| {
+        for err in errors {
+            error(err);
+        }
+    });
 
-    Ok(())
+    // locals are kept between runs, bcs closures created by earlier repl lines still need them
+    locals
+        .borrow_mut()
+        .extend(resolved.map_err(|_| RunError::ParseError)?);
+
+    Ok(statements)
 }
 
 pub fn run_prompt() -> Result<(), Box<dyn Error>> {
     let stdin = io::stdin();
     let input = &mut String::new();
     let environment = Rc::new(RefCell::new(Environment::new()));
+    let locals = Rc::new(RefCell::new(Locals::new()));
+    let modules = Rc::new(RefCell::new(Modules::new()));
     loop {
         input.clear();
         print!("> ");
         io::stdout().flush()?;
         stdin.read_line(input)?;
-        let _ = run(input, &environment);
+        let _ = run(input, &environment, &locals, &modules);
     }
 }
 
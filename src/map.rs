@@ -0,0 +1,111 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{interpreter::as_integer, token::LiteralType};
+
+// the hashable subset of LiteralType. Two values produce the same key exactly when
+// interpreter::is_equal considers them equal
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    String(String),
+    // integral numbers, floats with no fractional part included (and -0.0), so 1 and 1.0 are
+    // the same key
+    Int(i64),
+    // bits of the f64, only ever holds numbers with a fractional part or out of the i64 range
+    Number(u64),
+    Bool(bool),
+    Nil,
+}
+
+impl MapKey {
+    // NaN is rejected, it's never equal to itself so it couldn't be looked up again
+    pub fn from_literal(value: &LiteralType) -> Option<MapKey> {
+        match value {
+            LiteralType::String(s) => Some(MapKey::String(s.clone())),
+            LiteralType::Int(i) => Some(MapKey::Int(*i)),
+            LiteralType::Number(n) if n.is_nan() => None,
+            LiteralType::Number(n) => match as_integer(value) {
+                Some(i) => Some(MapKey::Int(i)),
+                None => Some(MapKey::Number(n.to_bits())),
+            },
+            LiteralType::Bool(b) => Some(MapKey::Bool(*b)),
+            LiteralType::Nil => Some(MapKey::Nil),
+            _ => None,
+        }
+    }
+
+    pub fn to_literal(&self) -> LiteralType {
+        match self {
+            MapKey::String(s) => LiteralType::String(s.clone()),
+            MapKey::Int(i) => LiteralType::Int(*i),
+            MapKey::Number(bits) => LiteralType::Number(f64::from_bits(*bits)),
+            MapKey::Bool(b) => LiteralType::Bool(*b),
+            MapKey::Nil => LiteralType::Nil,
+        }
+    }
+}
+
+// keeps the insertion order, so printing and iterating a map is deterministic
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    entries: Vec<(MapKey, LiteralType)>,
+    indices: HashMap<MapKey, usize>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<&LiteralType> {
+        self.indices.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn contains_key(&self, key: &MapKey) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: MapKey, value: LiteralType) {
+        match self.indices.get(&key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.indices.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &MapKey) -> Option<LiteralType> {
+        let index = self.indices.remove(key)?;
+        let (_, value) = self.entries.remove(index);
+
+        // everything after the removed entry moved one slot to the left
+        for (key, _) in &self.entries[index..] {
+            if let Some(i) = self.indices.get_mut(key) {
+                *i -= 1;
+            }
+        }
+
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&MapKey, &LiteralType)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+impl Display for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{")?;
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {value}", key.to_literal())?;
+        }
+        write!(f, "}}")
+    }
+}
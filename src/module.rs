@@ -0,0 +1,114 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use crate::{callable::Callable, environment::Environment, token::LiteralType};
+
+// imports without an extension get this one
+pub const EXTENSION: &str = "iz";
+
+// a file that has been run. Its top-level environment doubles as the namespace
+#[derive(Debug)]
+pub struct Module {
+    pub name: String,
+    pub path: PathBuf,
+    pub environment: Rc<RefCell<Environment>>,
+}
+
+impl Module {
+    pub fn new(path: PathBuf, environment: Rc<RefCell<Environment>>) -> Self {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Self {
+            name,
+            path,
+            environment,
+        }
+    }
+
+    // the natives are defined in every module's environment, but they aren't the module's own
+    // bindings, so they aren't exported. A module that redefines one exports its own
+    pub fn get(&self, name: &str) -> Option<LiteralType> {
+        let value = self.environment.borrow().get_local(name).flatten()?;
+        match &value {
+            LiteralType::Callable(Callable::NativeFunction(native))
+                if !native.is_bound() && native.name() == name =>
+            {
+                None
+            }
+            _ => Some(value),
+        }
+    }
+}
+
+impl Display for Module {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
+
+// every module that was imported during a run, keyed by its canonical path, so each one only
+// runs once
+#[derive(Debug, Default)]
+pub struct Modules {
+    cache: HashMap<PathBuf, Rc<Module>>,
+    // the modules that are still running, the last one is the innermost import. Importing one of
+    // them again is a cycle
+    loading: Vec<PathBuf>,
+}
+
+impl Modules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, path: &Path) -> Option<Rc<Module>> {
+        self.cache.get(path).cloned()
+    }
+
+    pub fn insert(&mut self, module: Rc<Module>) {
+        self.cache.insert(module.path.clone(), module);
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.cache.remove(path);
+    }
+
+    pub fn is_loading(&self, path: &Path) -> bool {
+        self.loading.iter().any(|loading| loading == path)
+    }
+
+    pub fn start_loading(&mut self, path: PathBuf) {
+        self.loading.push(path);
+    }
+
+    pub fn finish_loading(&mut self) {
+        self.loading.pop();
+    }
+
+    // the directory imports are relative to, found through the top-level environment the import
+    // runs in. Code that isn't in a file (the repl) imports relative to the working directory
+    pub fn base_dir(&self, globals: &Rc<RefCell<Environment>>) -> PathBuf {
+        self.cache
+            .values()
+            .find(|module| Rc::ptr_eq(&module.environment, globals))
+            .and_then(|module| module.path.parent())
+            .map_or_else(|| PathBuf::from("."), Path::to_path_buf)
+    }
+}
+
+// joins the import path to the importing file's directory and adds the default extension
+pub fn resolve_path(base_dir: &Path, import: &str) -> PathBuf {
+    let mut path = base_dir.join(import);
+    if path.extension().is_none() {
+        path.set_extension(EXTENSION);
+    }
+    path
+}
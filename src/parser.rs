@@ -1,7 +1,8 @@
-use std::fmt::Display;
+use std::{fmt::Display, mem, path::Path, rc::Rc};
 
 use crate::{
-    ast::{Expr, Stmt},
+    ast::{Expr, ExprId, MatchArm, Param, Pattern, Stmt},
+    scanner::is_identifier,
     token::{LiteralType, Token, TokenType},
     utils::{ScopeCall, defer, expr},
 };
@@ -55,10 +56,18 @@ impl Parser<'_> {
     }
 
     fn declaration(&mut self) -> Result<Stmt, ParseError> {
-        let stmt = if self.match_token(&[TokenType::Fun]) {
+        let stmt = if self.match_token(&[TokenType::Class]) {
+            self.class_declaration()
+        } else if self.check(TokenType::Fun) && self.check_next(TokenType::Identifier) {
+            // `fun` without a name is a lambda, which is parsed as an expression statement
+            self.advance();
             self.function("function")
         } else if self.match_token(&[TokenType::Var]) {
             self.var_declaration()
+        } else if self.match_token(&[TokenType::Const]) {
+            self.const_declaration()
+        } else if self.match_token(&[TokenType::Import]) {
+            self.import_declaration()
         } else {
             self.statement()
         };
@@ -66,13 +75,108 @@ impl Parser<'_> {
         stmt.inspect_err(|_| self.synchronize())
     }
 
+    fn import_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+
+        let mut names = Vec::new();
+        if !self.check(TokenType::String) {
+            loop {
+                names.push(self.consume(TokenType::Identifier, "Expect name to import.")?);
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+            self.consume(TokenType::From, "Expect 'from' after imported names.")?;
+        }
+
+        let path = self.consume(TokenType::String, "Expect module path.")?;
+        self.consume(TokenType::Semicolon, "Expect ';' after import.")?;
+
+        let Some(LiteralType::String(path_value)) = path.literal.as_deref() else {
+            unreachable!("Shouldn't happen. A string token always has a string literal")
+        };
+        let path_value = path_value.clone();
+
+        let namespace = if names.is_empty() {
+            // the namespace is named after the file, so it has to be a valid identifier
+            let name = Path::new(&path_value)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .filter(|stem| is_identifier(stem));
+            let Some(name) = name else {
+                return Err(ParseError {
+                    token: path,
+                    msg: "Module file name must be a valid identifier.".to_string(),
+                });
+            };
+            Some(Token::new(TokenType::Identifier, &name, None, path.line))
+        } else {
+            None
+        };
+
+        Ok(Stmt::Import {
+            keyword,
+            path: path_value,
+            namespace,
+            names,
+        })
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume(TokenType::Identifier, "Expect class name.")?;
+
+        let superclass = if self.match_token(&[TokenType::Less]) {
+            let name = self.consume(TokenType::Identifier, "Expect superclass name.")?;
+            Some(Expr::Variable {
+                id: ExprId::fresh(),
+                name,
+            })
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+
+        Ok(Stmt::Class {
+            name,
+            superclass,
+            methods,
+        })
+    }
+
     fn function(&mut self, kind: &str) -> Result<Stmt, ParseError> {
         let name = self.consume(TokenType::Identifier, &format!("Expect {} name.", kind))?;
         self.consume(
             TokenType::LeftParen,
             &format!("Expect '(' after {kind} name."),
         )?;
-        let mut params = Vec::new();
+        let params = self.parameters()?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {} body.", kind),
+        )?;
+        let body = self.function_body()?;
+        let is_generator = body.iter().any(Stmt::contains_yield);
+
+        Ok(Stmt::Function {
+            name,
+            params,
+            body,
+            is_generator,
+        })
+    }
+
+    // parses the parameter list up to and including the closing ')'
+    fn parameters(&mut self) -> Result<Vec<Param>, ParseError> {
+        let mut params: Vec<Param> = Vec::new();
         if !self.check(TokenType::RightParen) {
             loop {
                 if params.len() >= 255 {
@@ -82,7 +186,44 @@ impl Parser<'_> {
                     });
                 }
 
-                params.push(self.consume(TokenType::Identifier, "Expect parameter name.")?);
+                if self.match_token(&[TokenType::DotDotDot]) {
+                    let name =
+                        self.consume(TokenType::Identifier, "Expect parameter name after '...'.")?;
+                    params.push(Param {
+                        name,
+                        default: None,
+                        rest: true,
+                    });
+
+                    if self.check(TokenType::Comma) {
+                        return Err(ParseError {
+                            token: self.peek().clone(),
+                            msg: "Rest parameter must be the last one.".to_string(),
+                        });
+                    }
+                    break;
+                }
+
+                let name = self.consume(TokenType::Identifier, "Expect parameter name.")?;
+                let default = if self.match_token(&[TokenType::Equal]) {
+                    Some(self.assignment()?)
+                } else {
+                    None
+                };
+
+                if default.is_none() && params.iter().any(|param| param.default.is_some()) {
+                    return Err(ParseError {
+                        token: name,
+                        msg: "Parameter without a default can't follow one with a default."
+                            .to_string(),
+                    });
+                }
+
+                params.push(Param {
+                    name,
+                    default,
+                    rest: false,
+                });
 
                 if !self.match_token(&[TokenType::Comma]) {
                     break;
@@ -92,17 +233,35 @@ impl Parser<'_> {
 
         self.consume(TokenType::RightParen, "Expect ')' after parameters")?;
 
-        self.consume(
-            TokenType::LeftBrace,
-            &format!("Expect '{{' before {} body.", kind),
-        )?;
-
-        let body = self.block()?;
+        Ok(params)
+    }
 
-        Ok(Stmt::Function { name, params, body })
+    fn function_body(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        // loops don't reach into the function body, `break` and `continue` can't leave a call
+        let enclosing_loop_depth = mem::replace(&mut self.loop_depth, 0);
+        let body = self.block();
+        self.loop_depth = enclosing_loop_depth;
+        body
     }
 
     fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        if self.check(TokenType::LeftBracket) || self.check(TokenType::LeftBrace) {
+            let pattern = self.pattern()?;
+            let equals =
+                self.consume(TokenType::Equal, "Expect '=' after destructuring pattern.")?;
+            let initializer = self.expression()?;
+            self.consume(
+                TokenType::Semicolon,
+                "Expect ';' after variable declaration.",
+            )?;
+
+            return Ok(Stmt::VarPattern {
+                pattern,
+                equals,
+                initializer,
+            });
+        }
+
         let name = self.consume(TokenType::Identifier, "Expect variable name")?;
         let initializer = if self.match_token(&[TokenType::Equal]) {
             Some(self.expression()?)
@@ -118,6 +277,24 @@ impl Parser<'_> {
         Ok(Stmt::Var { name, initializer })
     }
 
+    fn const_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume(TokenType::Identifier, "Expect constant name.")?;
+        if !self.match_token(&[TokenType::Equal]) {
+            return Err(ParseError {
+                token: name,
+                msg: "Constant must have an initializer.".to_string(),
+            });
+        }
+        let initializer = self.expression()?;
+
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after constant declaration.",
+        )?;
+
+        Ok(Stmt::Const { name, initializer })
+    }
+
     fn statement(&mut self) -> Result<Stmt, ParseError> {
         if self.match_token(&[TokenType::For]) {
             return self.for_statement();
@@ -131,14 +308,32 @@ impl Parser<'_> {
         if self.match_token(&[TokenType::Return]) {
             return self.return_statement();
         }
+        if self.match_token(&[TokenType::Yield]) {
+            return self.yield_statement();
+        }
         if self.match_token(&[TokenType::While]) {
             return self.while_statement();
         }
+        if self.match_token(&[TokenType::Throw]) {
+            return self.throw_statement();
+        }
+        if self.match_token(&[TokenType::Match]) {
+            return self.match_statement();
+        }
+        if self.match_token(&[TokenType::Try]) {
+            return self.try_statement();
+        }
 
         if self.match_token(&[TokenType::Break]) {
             return self.break_statement();
         }
 
+        if self.match_token(&[TokenType::Continue]) {
+            return self.continue_statement();
+        }
+
+        // a '{' at the start of a statement is always a block, map literals are only parsed in
+        // expression position
         if self.match_token(&[TokenType::LeftBrace]) {
             return Ok(Stmt::Block {
                 statements: self.block()?,
@@ -172,6 +367,18 @@ impl Parser<'_> {
         Ok(Stmt::Break)
     }
 
+    fn continue_statement(&mut self) -> Result<Stmt, ParseError> {
+        if *self.loop_depth() == 0 {
+            return Err(ParseError {
+                token: self.previous().clone(),
+                msg: "Must be inside a loop to use 'continue'".to_string(),
+            });
+        }
+        self.consume(TokenType::Semicolon, "Expect ';' after 'continue'")?;
+
+        Ok(Stmt::Continue)
+    }
+
     fn if_statement(&mut self) -> Result<Stmt, ParseError> {
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
         let condition = self.expression()?;
@@ -198,15 +405,23 @@ impl Parser<'_> {
         self.consume(TokenType::RightParen, "Expect ')' after while condition.")?;
         let body = Box::new(self.statement()?);
         defer! {
-            *self.loop_depth() += 1;
+            *self.loop_depth() -= 1;
         }
 
-        Ok(Stmt::While { condition, body })
+        Ok(Stmt::While {
+            condition,
+            body,
+            increment: None,
+        })
     }
 
     fn for_statement(&mut self) -> Result<Stmt, ParseError> {
         self.loop_depth += 1;
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+        if self.check(TokenType::Identifier) && self.check_next(TokenType::In) {
+            return self.for_in_statement();
+        }
+
         let initializer = if self.match_token(&[TokenType::Semicolon]) {
             None
         } else if self.match_token(&[TokenType::Var]) {
@@ -215,7 +430,7 @@ impl Parser<'_> {
             Some(self.expression_statement()?)
         };
 
-        let condition = if !self.match_token(&[TokenType::Semicolon]) {
+        let condition = if !self.check(TokenType::Semicolon) {
             Some(self.expression()?)
         } else {
             None
@@ -223,7 +438,7 @@ impl Parser<'_> {
 
         self.consume(TokenType::Semicolon, "Expect ';' after loop condition")?;
 
-        let increment = if !self.match_token(&[TokenType::RightParen]) {
+        let increment = if !self.check(TokenType::RightParen) {
             Some(self.expression()?)
         } else {
             None
@@ -231,20 +446,17 @@ impl Parser<'_> {
 
         self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
 
-        let body = match increment {
-            Some(inc) => Stmt::Block {
-                statements: vec![self.statement()?, Stmt::Expression { expression: inc }],
-            },
-            None => self.statement()?,
-        };
+        let body = self.statement()?;
 
         let condition = condition.unwrap_or(Expr::Literal {
             value: LiteralType::Bool(true),
         });
 
+        // the increment is kept out of the body, so `continue` doesn't skip it
         let body = Stmt::While {
             condition,
             body: Box::new(body),
+            increment,
         };
 
         let body = match initializer {
@@ -261,6 +473,26 @@ impl Parser<'_> {
         Ok(body)
     }
 
+    // the part of `for (x in iterable) body` after the '('
+    fn for_in_statement(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.advance().clone();
+        let keyword = self.advance().clone();
+        let iterable = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after for-in iterable.")?;
+        let body = Box::new(self.statement()?);
+
+        defer! {
+            *self.loop_depth() -= 1;
+        }
+
+        Ok(Stmt::ForIn {
+            name,
+            keyword,
+            iterable,
+            body,
+        })
+    }
+
     fn print_statement(&mut self) -> Result<Stmt, ParseError> {
         let expression = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
@@ -281,6 +513,101 @@ impl Parser<'_> {
         Ok(Stmt::Return { keyword, value })
     }
 
+    fn yield_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let value = if !self.check(TokenType::Semicolon) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(TokenType::Semicolon, "Expect ';' after yield value.")?;
+
+        Ok(Stmt::Yield { keyword, value })
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after thrown value.")?;
+
+        Ok(Stmt::Throw { keyword, value })
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftBrace, "Expect '{' after 'try'.")?;
+        let body = self.block()?;
+
+        let catch = if self.match_token(&[TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "Expect '(' after 'catch'.")?;
+            let name = self.consume(TokenType::Identifier, "Expect exception name.")?;
+            self.consume(TokenType::RightParen, "Expect ')' after exception name.")?;
+            self.consume(TokenType::LeftBrace, "Expect '{' before catch body.")?;
+            Some((name, self.block()?))
+        } else {
+            None
+        };
+
+        let finally = if self.match_token(&[TokenType::Finally]) {
+            self.consume(TokenType::LeftBrace, "Expect '{' after 'finally'.")?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(ParseError {
+                token: keyword,
+                msg: "Expect 'catch' or 'finally' after try block.".to_string(),
+            });
+        }
+
+        Ok(Stmt::Try {
+            body,
+            catch,
+            finally,
+        })
+    }
+
+    fn match_statement(&mut self) -> Result<Stmt, ParseError> {
+        use TokenType::*;
+
+        let (keyword, subject) = self.match_subject()?;
+
+        let mut arms = Vec::new();
+        while !self.check(RightBrace) && !self.is_at_end() {
+            let (pattern, guard) = self.match_arm_head()?;
+
+            // a bare expression doesn't need a ';', so arms can be written like in the
+            // expression form
+            let body = if let LeftBrace | Print | Return | Yield | Break | Continue | Throw | If
+            | While | For | Try | Match = self.peek().t_type
+            {
+                self.statement()?
+            } else {
+                Stmt::Expression {
+                    expression: self.assignment()?,
+                }
+            };
+            self.match_token(&[Comma, Semicolon]);
+
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+        }
+
+        self.consume(RightBrace, "Expect '}' after match arms.")?;
+
+        Ok(Stmt::Match {
+            keyword,
+            subject,
+            arms,
+        })
+    }
+
     fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
         let expression = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
@@ -303,28 +630,69 @@ impl Parser<'_> {
         let expr = self.ternary()?;
 
         if self.match_token(&[TokenType::Equal]) {
+            let equals = self.previous().clone();
             let value = self.assignment()?;
-            let equals = self.previous();
 
-            if let Expr::Variable { name } = expr {
-                return Ok(Expr::Assign {
-                    name,
-                    value: Box::new(value),
-                });
+            match expr {
+                Expr::Variable { name, .. } => {
+                    return Ok(Expr::Assign {
+                        id: ExprId::fresh(),
+                        name,
+                        value: Box::new(value),
+                    });
+                }
+                Expr::Get { object, name } => {
+                    return Ok(Expr::Set {
+                        object,
+                        name,
+                        value: Box::new(value),
+                    });
+                }
+                Expr::Index {
+                    object,
+                    bracket,
+                    index,
+                } => {
+                    return Ok(Expr::SetIndex {
+                        object,
+                        bracket,
+                        index,
+                        value: Box::new(value),
+                    });
+                }
+                Expr::List { .. } if is_list_target(&expr) => {
+                    return Ok(Expr::AssignList {
+                        target: Box::new(expr),
+                        equals,
+                        value: Box::new(value),
+                    });
+                }
+                _ => (),
             }
             return Err(ParseError {
-                token: equals.clone(),
+                token: equals,
                 msg: "Invalid assignment target.".to_string(),
             });
         }
 
+        if self.match_token(&[
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+        ]) {
+            let op = self.previous().clone();
+            let value = self.assignment()?;
+            return compound_assignment(expr, op, value, false);
+        }
+
         Ok(expr)
     }
 
     // ternary -> equality ("?" expression : ternary)? // expression grammar
     fn ternary(&mut self) -> Result<Expr, ParseError> {
         use TokenType::*;
-        let expr = self.or()?;
+        let expr = self.coalesce()?;
 
         if self.match_token(&[Question]) {
             let second = self.expression()?;
@@ -340,6 +708,25 @@ impl Parser<'_> {
         Ok(expr)
     }
 
+    // coalesce -> or ( "??" or )* ;
+    // looser than `or`, so `a or b ?? c` is `(a or b) ?? c`. The scanner keeps `??` apart from
+    // the ternary's `?`
+    fn coalesce(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.or()?;
+
+        while self.match_token(&[TokenType::QuestionQuestion]) {
+            let op = self.previous().clone();
+            let right = self.or()?;
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
     fn or(&mut self) -> Result<Expr, ParseError> {
         let mut expr = self.and()?;
 
@@ -378,7 +765,47 @@ impl Parser<'_> {
 
     fn comparison(&mut self) -> Result<Expr, ParseError> {
         use TokenType::*;
-        self.left_association_binary(&[Greater, GreaterEqual, Less, LessEqual], Self::term)
+        self.left_association_binary(&[Greater, GreaterEqual, Less, LessEqual, In], Self::range)
+    }
+
+    // range -> bitwise_or (( ".." | "..=" ) bitwise_or)? ;
+    // `a..b..c` doesn't mean anything, so ranges don't chain
+    fn range(&mut self) -> Result<Expr, ParseError> {
+        use TokenType::*;
+        let expr = self.bitwise_or()?;
+
+        if self.match_token(&[DotDot, DotDotEqual]) {
+            let op = self.previous().clone();
+            let right = self.bitwise_or()?;
+            return Ok(Expr::Binary {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    // bitwise operators sit between ranges and term, loosest first: | ^ & and the shifts
+    fn bitwise_or(&mut self) -> Result<Expr, ParseError> {
+        use TokenType::*;
+        self.left_association_binary(&[Pipe], Self::bitwise_xor)
+    }
+
+    fn bitwise_xor(&mut self) -> Result<Expr, ParseError> {
+        use TokenType::*;
+        self.left_association_binary(&[Caret], Self::bitwise_and)
+    }
+
+    fn bitwise_and(&mut self) -> Result<Expr, ParseError> {
+        use TokenType::*;
+        self.left_association_binary(&[Ampersand], Self::shift)
+    }
+
+    fn shift(&mut self) -> Result<Expr, ParseError> {
+        use TokenType::*;
+        self.left_association_binary(&[LessLess, GreaterGreater], Self::term)
     }
 
     fn term(&mut self) -> Result<Expr, ParseError> {
@@ -388,12 +815,18 @@ impl Parser<'_> {
 
     fn factor(&mut self) -> Result<Expr, ParseError> {
         use TokenType::*;
-        self.left_association_binary(&[Slash, Star], Self::unary)
+        self.left_association_binary(&[Slash, Star, Percent, TildeSlash], Self::unary)
     }
 
     fn unary(&mut self) -> Result<Expr, ParseError> {
         use TokenType::*;
-        if self.match_token(&[Bang, Minus]) {
+        if self.match_token(&[PlusPlus, MinusMinus]) {
+            let op = self.previous().clone();
+            let target = self.unary()?;
+            return compound_assignment(target, op, increment_literal(), false);
+        }
+
+        if self.match_token(&[Bang, Minus, Tilde]) {
             let op = self.previous().clone();
             let right = self.unary()?;
             return Ok(Expr::Unary {
@@ -402,34 +835,118 @@ impl Parser<'_> {
             });
         }
 
-        self.call()
+        self.power()
+    }
+
+    // power -> call ("**" unary)? ;
+    // binds tighter than a unary operator on its left, so -2 ** 2 is -(2 ** 2). Recursing into
+    // unary for the right operand makes it right associative and allows 2 ** -1
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.postfix()?;
+
+        if self.match_token(&[TokenType::StarStar]) {
+            let op = self.previous().clone();
+            let right = self.unary()?;
+            return Ok(Expr::Binary {
+                left: Box::new(expr),
+                op,
+                right: Box::new(right),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn postfix(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.call()?;
+
+        if self.match_token(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let op = self.previous().clone();
+            return compound_assignment(expr, op, increment_literal(), true);
+        }
+
+        Ok(expr)
     }
 
     fn call(&mut self) -> Result<Expr, ParseError> {
         let mut expr = self.primary()?;
+        let mut optional = false;
 
         loop {
-            if self.match_token(&[TokenType::LeftParen]) {
+            if self.match_token(&[TokenType::QuestionDot]) {
+                optional = true;
+                let guarded = Expr::NilGuard {
+                    expression: Box::new(expr),
+                };
+
+                // `f?.(args)` or `obj?.field`
+                expr = if self.match_token(&[TokenType::LeftParen]) {
+                    self.finish_call(guarded)?
+                } else {
+                    let name =
+                        self.consume(TokenType::Identifier, "Expect property name after '?.'.")?;
+                    Expr::Get {
+                        object: Box::new(guarded),
+                        name,
+                    }
+                };
+            } else if self.match_token(&[TokenType::LeftParen]) {
                 expr = self.finish_call(expr)?;
+            } else if self.match_token(&[TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self.consume(TokenType::RightBracket, "Expect ']' after index.")?;
+                expr = Expr::Index {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                };
+            } else if self.match_token(&[TokenType::Dot]) {
+                let name =
+                    self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
+                expr = Expr::Get {
+                    object: Box::new(expr),
+                    name,
+                };
             } else {
                 break;
             }
         }
 
+        // the chain ends here, so a guard that found nil skips everything up to this point
+        if optional {
+            expr = Expr::OptionalChain {
+                expression: Box::new(expr),
+            };
+        }
+
         Ok(expr)
     }
 
     fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
         let mut args = Vec::new();
+        let mut named = Vec::new();
         if !self.check(TokenType::RightParen) {
             loop {
-                if args.len() >= 255 {
+                if args.len() + named.len() >= 255 {
                     return Err(ParseError {
                         token: self.peek().clone(),
                         msg: "Can't have more than 255 arguments".to_string(),
                     });
                 }
-                args.push(self.equality()?);
+
+                if self.check(TokenType::Identifier) && self.check_next(TokenType::Colon) {
+                    let name = self.advance().clone();
+                    self.advance();
+                    named.push((name, self.equality()?));
+                } else if let Some((name, _)) = named.first() {
+                    return Err(ParseError {
+                        token: name.clone(),
+                        msg: "Positional arguments can't follow named ones.".to_string(),
+                    });
+                } else {
+                    args.push(self.equality()?);
+                }
+
                 if !self.match_token(&[TokenType::Comma]) {
                     break;
                 }
@@ -441,6 +958,7 @@ impl Parser<'_> {
             callee: Box::new(callee),
             paren,
             args,
+            named,
         })
     }
 
@@ -475,11 +993,47 @@ impl Parser<'_> {
             ));
         }
 
+        if self.match_token(&[Interpolation]) {
+            return self.interpolation();
+        }
+
         // i included the enum name bcs of ambiguity of LiteralType and TokenType
         if self.match_token(&[TokenType::Nil]) {
             return Ok(create_literal(LiteralType::Nil));
         }
 
+        if self.match_token(&[LeftBracket]) {
+            let mut elements = Vec::new();
+            if !self.check(RightBracket) {
+                loop {
+                    elements.push(self.assignment()?);
+                    if !self.match_token(&[Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(RightBracket, "Expect ']' after list elements.")?;
+            return Ok(Expr::List { elements });
+        }
+
+        if self.match_token(&[LeftBrace]) {
+            let brace = self.previous().clone();
+            let mut entries = Vec::new();
+            if !self.check(RightBrace) {
+                loop {
+                    let key = self.assignment()?;
+                    self.consume(Colon, "Expect ':' after map key.")?;
+                    let value = self.assignment()?;
+                    entries.push((key, value));
+                    if !self.match_token(&[Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(RightBrace, "Expect '}' after map entries.")?;
+            return Ok(Expr::Map { brace, entries });
+        }
+
         if self.match_token(&[LeftParen]) {
             let expr = self.expression()?;
             self.consume(RightParen, "Expect ')' after expression")?;
@@ -488,8 +1042,35 @@ impl Parser<'_> {
             });
         }
 
+        if self.match_token(&[Fun]) {
+            return self.lambda();
+        }
+
+        if self.match_token(&[Match]) {
+            return self.match_expression();
+        }
+
+        if self.match_token(&[Super]) {
+            let keyword = self.previous().clone();
+            self.consume(Dot, "Expect '.' after 'super'.")?;
+            let method = self.consume(Identifier, "Expect superclass method name.")?;
+            return Ok(Expr::Super {
+                id: ExprId::fresh(),
+                keyword,
+                method,
+            });
+        }
+
+        if self.match_token(&[This]) {
+            return Ok(Expr::This {
+                id: ExprId::fresh(),
+                keyword: self.previous().clone(),
+            });
+        }
+
         if self.match_token(&[Identifier]) {
             return Ok(Expr::Variable {
+                id: ExprId::fresh(),
                 name: self.previous().clone(),
             });
         }
@@ -518,7 +1099,7 @@ impl Parser<'_> {
             });
         }
 
-        if self.match_token(&[Star, Slash]) {
+        if self.match_token(&[Star, Slash, Percent, TildeSlash, StarStar]) {
             let _ = self.factor();
             return Err(ParseError {
                 token: self.previous().clone(),
@@ -532,6 +1113,64 @@ impl Parser<'_> {
         })
     }
 
+    // interpolation -> ( INTERPOLATION expression )+ STRING
+    fn interpolation(&mut self) -> Result<Expr, ParseError> {
+        let mut parts = Vec::new();
+        loop {
+            parts.push(Expr::Literal {
+                value: *self
+                    .previous()
+                    .literal
+                    .clone()
+                    .expect("The interpolation token should have a literal"),
+            });
+            parts.push(self.expression()?);
+
+            if !self.match_token(&[TokenType::Interpolation]) {
+                break;
+            }
+        }
+
+        let end = self.consume(
+            TokenType::String,
+            "Expect '}' after interpolated expression.",
+        )?;
+        parts.push(Expr::Literal {
+            value: *end.literal.expect("The string token should have a literal"),
+        });
+
+        Ok(Expr::Interpolation { parts })
+    }
+
+    // lambda -> "fun" "(" parameters? ")" ( block | "=>" assignment )
+    fn lambda(&mut self) -> Result<Expr, ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'fun'.")?;
+        let params = self.parameters()?;
+
+        let body = if self.match_token(&[TokenType::Arrow]) {
+            // the expression-bodied form just returns its expression
+            let arrow = self.previous().clone();
+            let value = self.assignment()?;
+            vec![Stmt::Return {
+                keyword: arrow,
+                value: Some(value),
+            }]
+        } else {
+            self.consume(TokenType::LeftBrace, "Expect '{' before lambda body.")?;
+            self.function_body()?
+        };
+
+        let is_generator = body.iter().any(Stmt::contains_yield);
+
+        Ok(Expr::Lambda {
+            keyword,
+            params,
+            body: Rc::new(body),
+            is_generator,
+        })
+    }
+
     fn consume(&mut self, t_type: TokenType, err_msg: &str) -> Result<Token, ParseError> {
         if self.check(t_type) {
             return Ok(self.advance().clone());
@@ -543,6 +1182,184 @@ impl Parser<'_> {
         })
     }
 
+    fn match_expression(&mut self) -> Result<Expr, ParseError> {
+        let (keyword, subject) = self.match_subject()?;
+
+        let mut arms = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            let (pattern, guard) = self.match_arm_head()?;
+            let body = self.assignment()?;
+            arms.push(MatchArm {
+                pattern,
+                guard,
+                body,
+            });
+
+            if !self.match_token(&[TokenType::Comma]) {
+                break;
+            }
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after match arms.")?;
+
+        Ok(Expr::Match {
+            keyword,
+            subject: Box::new(subject),
+            arms,
+        })
+    }
+
+    // `match (subject) {`, shared by the statement and the expression form
+    fn match_subject(&mut self) -> Result<(Token, Expr), ParseError> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftParen, "Expect '(' after 'match'.")?;
+        let subject = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after match value.")?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before match arms.")?;
+
+        Ok((keyword, subject))
+    }
+
+    // `pattern if guard =>`
+    fn match_arm_head(&mut self) -> Result<(Pattern, Option<Expr>), ParseError> {
+        let pattern = self.pattern()?;
+        let guard = if self.match_token(&[TokenType::If]) {
+            Some(self.assignment()?)
+        } else {
+            None
+        };
+        self.consume(TokenType::Arrow, "Expect '=>' after match pattern.")?;
+
+        Ok((pattern, guard))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        let first = self.single_pattern()?;
+        if !self.check(TokenType::Pipe) {
+            return Ok(first);
+        }
+
+        let mut alternatives = vec![first];
+        while self.match_token(&[TokenType::Pipe]) {
+            alternatives.push(self.single_pattern()?);
+        }
+
+        // only one alternative matches, so the others would leave their names unbound
+        if let Some(name) = alternatives.iter().flat_map(Pattern::bindings).next() {
+            return Err(ParseError {
+                token: name.clone(),
+                msg: "Alternative patterns can't bind names.".to_string(),
+            });
+        }
+
+        Ok(Pattern::Or(alternatives))
+    }
+
+    fn single_pattern(&mut self) -> Result<Pattern, ParseError> {
+        use TokenType::*;
+
+        if let Some(literal) = self.literal_pattern()? {
+            return Ok(Pattern::Literal(literal));
+        }
+
+        if self.match_token(&[Identifier]) {
+            let name = self.previous().clone();
+            if name.lexeme == "_" {
+                return Ok(Pattern::Wildcard);
+            }
+            return Ok(Pattern::Binding(name));
+        }
+
+        if self.match_token(&[LeftBracket]) {
+            let mut elements = Vec::new();
+            let mut rest = None;
+            if !self.check(RightBracket) {
+                loop {
+                    // the rest is always the last element
+                    if self.match_token(&[DotDotDot]) {
+                        rest = Some(self.consume(Identifier, "Expect name after '...'.")?);
+                        break;
+                    }
+                    elements.push(self.pattern()?);
+                    if !self.match_token(&[Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(RightBracket, "Expect ']' after list pattern.")?;
+
+            return Ok(Pattern::List { elements, rest });
+        }
+
+        if self.match_token(&[LeftBrace]) {
+            let mut entries = Vec::new();
+            if !self.check(RightBrace) {
+                loop {
+                    // `{name}` is short for `{"name": name}`
+                    if self.check(Identifier) && !self.check_next(Colon) {
+                        let name = self.advance().clone();
+                        let key = LiteralType::String(name.lexeme.clone());
+                        entries.push((key, Pattern::Binding(name)));
+                    } else {
+                        let Some(key) = self.literal_pattern()? else {
+                            return Err(ParseError {
+                                token: self.peek().clone(),
+                                msg: "Map pattern keys must be literals.".to_string(),
+                            });
+                        };
+                        self.consume(Colon, "Expect ':' after map pattern key.")?;
+                        entries.push((key, self.pattern()?));
+                    }
+
+                    if !self.match_token(&[Comma]) {
+                        break;
+                    }
+                }
+            }
+            self.consume(RightBrace, "Expect '}' after map pattern.")?;
+
+            return Ok(Pattern::Map { entries });
+        }
+
+        Err(ParseError {
+            token: self.peek().clone(),
+            msg: "Expect pattern.".to_string(),
+        })
+    }
+
+    // numbers (negative ones too), strings, booleans and nil
+    fn literal_pattern(&mut self) -> Result<Option<LiteralType>, ParseError> {
+        use TokenType::*;
+
+        if self.match_token(&[Number, String]) {
+            let literal = self
+                .previous()
+                .literal
+                .clone()
+                .expect("The number and string token should have a literal");
+            return Ok(Some(*literal));
+        }
+
+        if self.match_token(&[Minus]) {
+            let number = self.consume(Number, "Expect number after '-' in pattern.")?;
+            return Ok(match number.literal.as_deref() {
+                Some(LiteralType::Int(value)) => Some(LiteralType::Int(-value)),
+                Some(LiteralType::Number(value)) => Some(LiteralType::Number(-value)),
+                _ => unreachable!("Shouldn't happen. A number token always has a number literal"),
+            });
+        }
+
+        if self.match_token(&[True, False]) {
+            return Ok(Some(LiteralType::Bool(self.previous().t_type == True)));
+        }
+
+        if self.match_token(&[Nil]) {
+            return Ok(Some(LiteralType::Nil));
+        }
+
+        Ok(None)
+    }
+
     // used for error recovery
     fn synchronize(&mut self) {
         use TokenType::*;
@@ -553,7 +1370,9 @@ impl Parser<'_> {
                 return;
             }
 
-            if let Class | Fun | Var | For | If | While | Print | Return = self.peek().t_type {
+            if let Class | Fun | Var | Const | For | If | While | Print | Return | Yield | Throw
+            | Try | Import = self.peek().t_type
+            {
                 return;
             }
             self.advance();
@@ -604,6 +1423,12 @@ impl Parser<'_> {
         self.previous()
     }
 
+    fn check_next(&self, t_type: TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|token| token.t_type == t_type)
+    }
+
     fn is_at_end(&self) -> bool {
         matches!(self.peek().t_type, TokenType::EOF)
     }
@@ -616,3 +1441,54 @@ impl Parser<'_> {
         &self.tokens[self.current - 1]
     }
 }
+
+// every element of the list can be assigned to
+fn is_list_target(expr: &Expr) -> bool {
+    match expr {
+        Expr::Variable { .. } | Expr::Get { .. } | Expr::Index { .. } => true,
+        Expr::List { elements } => elements.iter().all(is_list_target),
+        _ => false,
+    }
+}
+
+// builds the node shared by `op=`, `++` and `--`. The operator token keeps its lexeme for error
+// messages, but gets the type of the arithmetic operation it performs
+fn compound_assignment(
+    target: Expr,
+    op: Token,
+    value: Expr,
+    postfix: bool,
+) -> Result<Expr, ParseError> {
+    use TokenType::*;
+
+    if !matches!(
+        target,
+        Expr::Variable { .. } | Expr::Get { .. } | Expr::Index { .. }
+    ) {
+        return Err(ParseError {
+            token: op,
+            msg: "Invalid assignment target.".to_string(),
+        });
+    }
+
+    let t_type = match op.t_type {
+        PlusEqual | PlusPlus => Plus,
+        MinusEqual | MinusMinus => Minus,
+        StarEqual => Star,
+        SlashEqual => Slash,
+        _ => unreachable!("Not a compound assignment operator"),
+    };
+
+    Ok(Expr::CompoundAssign {
+        target: Box::new(target),
+        op: Token { t_type, ..op },
+        value: Box::new(value),
+        postfix,
+    })
+}
+
+fn increment_literal() -> Expr {
+    Expr::Literal {
+        value: LiteralType::Int(1),
+    }
+}
@@ -5,12 +5,45 @@ pub fn pretty_print(expr: &Expr) -> String {
     match expr {
         Expr::Binary { left, op, right } => parenthesize(&op.lexeme, &[left, right]),
         Expr::Grouping { expression } => parenthesize("group", &[expression]),
+        Expr::NilGuard { expression } => parenthesize("?", &[expression]),
+        Expr::OptionalChain { expression } => parenthesize("?.", &[expression]),
+        Expr::Index { object, index, .. } => parenthesize("[]", &[object, index]),
+        Expr::SetIndex {
+            object,
+            index,
+            value,
+            ..
+        } => parenthesize("[]=", &[object, index, value]),
+        Expr::Interpolation { parts } => {
+            let parts: Vec<&Expr> = parts.iter().collect();
+            parenthesize("interpolation", &parts)
+        }
+        Expr::Map { entries, .. } => {
+            let entries: Vec<&Expr> = entries.iter().flat_map(|(k, v)| [k, v]).collect();
+            parenthesize("map", &entries)
+        }
+        Expr::List { elements } => {
+            let elements: Vec<&Expr> = elements.iter().collect();
+            parenthesize("list", &elements)
+        }
+        Expr::Lambda { params, .. } => {
+            let params: Vec<&str> = params.iter().map(|p| p.name.lexeme.as_str()).collect();
+            format!("(fun ({}))", params.join(" "))
+        }
         Expr::Literal { value } => match value {
             LiteralType::String(v) => v.to_string(),
+            LiteralType::Int(v) => v.to_string(),
             LiteralType::Number(v) => v.to_string(),
             LiteralType::Bool(v) => v.to_string(),
             LiteralType::Nil => "Nil".to_string(),
-            LiteralType::Callable(_) => todo!(),
+            LiteralType::Callable(_)
+            | LiteralType::Class(_)
+            | LiteralType::Instance(_)
+            | LiteralType::List(_)
+            | LiteralType::Map(_)
+            | LiteralType::Module(_)
+            | LiteralType::Generator(_)
+            | LiteralType::Range(_) => todo!(),
         },
         Expr::Unary { op, right } => parenthesize(&op.lexeme, &[right]),
         Expr::Ternary {
@@ -18,14 +51,34 @@ pub fn pretty_print(expr: &Expr) -> String {
             second,
             third,
         } => parenthesize("?:", &[first, second, third]),
-        Expr::Variable { name } => name.lexeme.clone(),
-        Expr::Assign { name, value } => parenthesize(&name.lexeme, &[value]),
+        Expr::Variable { name, .. } => name.lexeme.clone(),
+        Expr::Assign { name, value, .. } => parenthesize(&name.lexeme, &[value]),
         Expr::Logical { left, op, right } => parenthesize(&op.lexeme, &[left, right]),
+        Expr::AssignList { target, value, .. } => parenthesize("=", &[target, value]),
+        // patterns and guards are left out, only the arm bodies are printed
+        Expr::Match { subject, arms, .. } => {
+            let exprs: Vec<&Expr> = std::iter::once(subject.as_ref())
+                .chain(arms.iter().map(|arm| &arm.body))
+                .collect();
+            parenthesize("match", &exprs)
+        }
+        Expr::CompoundAssign {
+            target, op, value, ..
+        } => parenthesize(&op.lexeme, &[target, value]),
         Expr::Call {
             callee: _,
             paren: _,
             args: _,
+            named: _,
         } => todo!(),
+        Expr::Get { object, name } => parenthesize(&format!(".{}", name.lexeme), &[object]),
+        Expr::Set {
+            object,
+            name,
+            value,
+        } => parenthesize(&format!(".{}=", name.lexeme), &[object, value]),
+        Expr::Super { method, .. } => format!("super.{}", method.lexeme),
+        Expr::This { .. } => "this".to_string(),
     }
 }
 
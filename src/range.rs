@@ -0,0 +1,100 @@
+use std::fmt::Display;
+
+use crate::{
+    callable::{Callable, NativeFunction},
+    interpreter::{InterpreterSignal, RuntimeError, as_integer},
+    token::{LiteralType, Token},
+};
+
+// `start..end` and `start..=end`. Nothing is generated up front, the elements are computed from
+// the bounds whenever they're needed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub start: i64,
+    pub end: i64,
+    // never 0, a negative step counts down from start to end
+    pub step: i64,
+    pub inclusive: bool,
+}
+
+impl Range {
+    pub fn new(start: i64, end: i64, inclusive: bool) -> Self {
+        Self {
+            start,
+            end,
+            step: 1,
+            inclusive,
+        }
+    }
+
+    // i128, so the distance between any two i64 fits
+    fn distance(&self, value: i64) -> i128 {
+        let distance = value as i128 - self.start as i128;
+        if self.step > 0 { distance } else { -distance }
+    }
+
+    pub fn len(&self) -> u64 {
+        let distance = self.distance(self.end);
+        let step = self.step.unsigned_abs() as i128;
+        let len = match self.inclusive {
+            true if distance >= 0 => distance / step + 1,
+            false if distance > 0 => (distance + step - 1) / step,
+            _ => 0,
+        };
+        len as u64
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        let distance = self.distance(value);
+        let step = self.step.unsigned_abs() as i128;
+        distance >= 0 && distance % step == 0 && ((distance / step) as u64) < self.len()
+    }
+
+    // the element at the index, which has to be less than the length
+    pub fn nth(&self, index: u64) -> i64 {
+        (self.start as i128 + index as i128 * self.step as i128) as i64
+    }
+
+    // `range.step(n)`
+    pub fn get(range: &Range, name: &Token) -> Result<LiteralType, RuntimeError> {
+        match name.lexeme.as_str() {
+            "step" => {
+                let step = step_function().bind(LiteralType::Range(*range));
+                Ok(LiteralType::Callable(Callable::NativeFunction(step)))
+            }
+            _ => Err(RuntimeError::new(
+                name,
+                format!("Undefined property {}.", name.lexeme),
+            )),
+        }
+    }
+}
+
+impl Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = if self.inclusive { "..=" } else { ".." };
+        write!(f, "{}{op}{}", self.start, self.end)?;
+        if self.step != 1 {
+            write!(f, " step {}", self.step)?;
+        }
+        Ok(())
+    }
+}
+
+// a copy of the range with another step
+fn step_function() -> NativeFunction {
+    let step = |args: &[LiteralType]| {
+        let LiteralType::Range(range) = &args[0] else {
+            unreachable!("Shouldn't happen. step is only bound to ranges")
+        };
+
+        match as_integer(&args[1]) {
+            Some(step) if step != 0 => Ok(LiteralType::Range(Range { step, ..*range })),
+            _ => Err(InterpreterSignal::NativeRuntimeError {
+                msg: "Range step must be a non-zero integer.".to_string(),
+            }),
+        }
+    };
+
+    NativeFunction::new("step".to_string(), 2, step)
+}
@@ -0,0 +1,664 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    ast::{Expr, ExprId, MatchArm, Param, Stmt},
+    parser::ParseError,
+    token::Token,
+};
+
+// maps every resolved local variable expression to the number of environments between its use
+// and its declaration. Globals are left out and looked up dynamically
+pub type Locals = HashMap<ExprId, usize>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Variable {
+    // the initializer hasn't been resolved yet
+    Declared,
+    Defined,
+    Constant,
+}
+
+pub struct Resolver {
+    scopes: Vec<HashMap<String, Variable>>,
+    // globals aren't in any scope, so the top-level constants are tracked separately. Only the
+    // ones declared before the assignment in source order are caught here, the rest at runtime
+    global_constants: HashSet<String>,
+    locals: Locals,
+    current_function: FunctionType,
+    current_class: ClassType,
+    errors: Vec<ParseError>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: Vec::new(),
+            global_constants: HashSet::new(),
+            locals: HashMap::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            errors: Vec::new(),
+        }
+    }
+
+    // static errors are reported as parse errors, so they go through the same reporting path
+    pub fn resolve(mut self, statements: &[Stmt]) -> Result<Locals, Vec<ParseError>> {
+        self.resolve_statements(statements);
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        Ok(self.locals)
+    }
+
+    fn resolve_statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn resolve_statement(&mut self, statement: &Stmt) {
+        match statement {
+            Stmt::Block { statements } => {
+                self.begin_scope();
+                self.resolve_statements(statements);
+                self.end_scope();
+            }
+            Stmt::Break | Stmt::Continue => (),
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let enclosing_class = self.current_class;
+                self.current_class = ClassType::Class;
+
+                self.declare(name);
+                self.define(name);
+
+                if let Some(superclass) = superclass {
+                    if let Expr::Variable {
+                        name: superclass_name,
+                        ..
+                    } = superclass
+                        && superclass_name.lexeme == name.lexeme
+                    {
+                        self.error(superclass_name, "A class can't inherit from itself.");
+                    }
+
+                    self.current_class = ClassType::Subclass;
+                    self.resolve_expression(superclass);
+
+                    self.begin_scope();
+                    if let Some(scope) = self.scopes.last_mut() {
+                        scope.insert("super".to_string(), Variable::Defined);
+                    }
+                }
+
+                self.begin_scope();
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.insert("this".to_string(), Variable::Defined);
+                }
+
+                for method in methods {
+                    if let Stmt::Function {
+                        name, params, body, ..
+                    } = method
+                    {
+                        let f_type = if name.lexeme == "init" {
+                            FunctionType::Initializer
+                        } else {
+                            FunctionType::Method
+                        };
+                        self.resolve_function(params, body, f_type);
+                    }
+                }
+
+                self.end_scope();
+
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+
+                self.current_class = enclosing_class;
+            }
+            Stmt::Expression { expression } | Stmt::Print { expression } => {
+                self.resolve_expression(expression)
+            }
+            Stmt::Function {
+                name, params, body, ..
+            } => {
+                // defined before the body is resolved, so the function can refer to itself
+                self.declare(name);
+                self.define(name);
+                self.resolve_function(params, body, FunctionType::Function);
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.resolve_expression(condition);
+                self.resolve_statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_statement(else_branch);
+                }
+            }
+            Stmt::Return { keyword, value } => {
+                if self.current_function == FunctionType::None {
+                    self.error(keyword, "Can't return from top-level code.");
+                }
+
+                if let Some(value) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.error(keyword, "Can't return a value from an initializer.");
+                    }
+                    self.resolve_expression(value);
+                }
+            }
+            Stmt::Yield { keyword, value } => {
+                match self.current_function {
+                    FunctionType::None => self.error(keyword, "Can't yield from top-level code."),
+                    FunctionType::Initializer => {
+                        self.error(keyword, "Can't yield from an initializer.")
+                    }
+                    FunctionType::Function | FunctionType::Method => (),
+                }
+
+                if let Some(value) = value {
+                    self.resolve_expression(value);
+                }
+            }
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+                ..
+            } => {
+                self.resolve_expression(iterable);
+
+                self.begin_scope();
+                self.declare(name);
+                self.define(name);
+                self.resolve_statement(body);
+                self.end_scope();
+            }
+            Stmt::Import {
+                namespace, names, ..
+            } => {
+                for name in namespace.iter().chain(names) {
+                    self.declare(name);
+                    self.define(name);
+                }
+            }
+            Stmt::Match { subject, arms, .. } => {
+                self.resolve_expression(subject);
+                for arm in arms {
+                    self.resolve_match_arm(arm, |resolver, body| resolver.resolve_statement(body));
+                }
+            }
+            Stmt::Throw { value, .. } => self.resolve_expression(value),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.begin_scope();
+                self.resolve_statements(body);
+                self.end_scope();
+
+                // the caught value and the catch body share one scope, like params and a body
+                if let Some((name, catch_body)) = catch {
+                    self.begin_scope();
+                    self.declare(name);
+                    self.define(name);
+                    self.resolve_statements(catch_body);
+                    self.end_scope();
+                }
+
+                if let Some(finally) = finally {
+                    self.begin_scope();
+                    self.resolve_statements(finally);
+                    self.end_scope();
+                }
+            }
+            Stmt::VarPattern {
+                pattern,
+                initializer,
+                ..
+            } => {
+                self.resolve_expression(initializer);
+                for name in pattern.bindings() {
+                    self.declare(name);
+                    self.define(name);
+                }
+            }
+            Stmt::Const { name, initializer } => {
+                self.declare(name);
+                self.resolve_expression(initializer);
+                self.define_constant(name);
+            }
+            Stmt::Var { name, initializer } => {
+                self.declare(name);
+                if let Some(initializer) = initializer {
+                    self.resolve_expression(initializer);
+                }
+                self.define(name);
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+            } => {
+                self.resolve_expression(condition);
+                self.resolve_statement(body);
+                if let Some(increment) = increment {
+                    self.resolve_expression(increment);
+                }
+            }
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &Expr) {
+        match expression {
+            Expr::Ternary {
+                first,
+                second,
+                third,
+            } => {
+                self.resolve_expression(first);
+                self.resolve_expression(second);
+                self.resolve_expression(third);
+            }
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            Expr::Call {
+                callee,
+                args,
+                named,
+                ..
+            } => {
+                self.resolve_expression(callee);
+                for arg in args.iter().chain(named.iter().map(|(_, value)| value)) {
+                    self.resolve_expression(arg);
+                }
+            }
+            Expr::Get { object, .. } => self.resolve_expression(object),
+            Expr::Set { object, value, .. } => {
+                self.resolve_expression(value);
+                self.resolve_expression(object);
+            }
+            Expr::Super { id, keyword, .. } => {
+                match self.current_class {
+                    ClassType::None => {
+                        self.error(keyword, "Can't use 'super' outside of a class.");
+                        return;
+                    }
+                    ClassType::Class => {
+                        self.error(keyword, "Can't use 'super' in a class with no superclass.");
+                        return;
+                    }
+                    ClassType::Subclass => (),
+                }
+
+                self.resolve_local(*id, keyword);
+            }
+            Expr::This { id, keyword } => {
+                if self.current_class == ClassType::None {
+                    self.error(keyword, "Can't use 'this' outside of a class.");
+                    return;
+                }
+
+                self.resolve_local(*id, keyword);
+            }
+            Expr::Grouping { expression }
+            | Expr::NilGuard { expression }
+            | Expr::OptionalChain { expression } => self.resolve_expression(expression),
+            Expr::Index { object, index, .. } => {
+                self.resolve_expression(object);
+                self.resolve_expression(index);
+            }
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                self.resolve_expression(value);
+                self.resolve_expression(object);
+                self.resolve_expression(index);
+            }
+            Expr::Map { entries, .. } => {
+                for (key, value) in entries {
+                    self.resolve_expression(key);
+                    self.resolve_expression(value);
+                }
+            }
+            Expr::List { elements } | Expr::Interpolation { parts: elements } => {
+                for element in elements {
+                    self.resolve_expression(element);
+                }
+            }
+            Expr::Lambda { params, body, .. } => {
+                self.resolve_function(params, body, FunctionType::Function)
+            }
+            Expr::Literal { .. } => (),
+            Expr::Unary { right, .. } => self.resolve_expression(right),
+            Expr::Variable { id, name } => {
+                if self
+                    .scopes
+                    .last()
+                    .and_then(|scope| scope.get(&name.lexeme))
+                    .is_some_and(|variable| *variable == Variable::Declared)
+                {
+                    self.error(name, "Can't read local variable in its own initializer.");
+                }
+
+                self.resolve_local(*id, name);
+            }
+            Expr::Assign { id, name, value } => {
+                self.resolve_expression(value);
+                self.check_assignable(name);
+                self.resolve_local(*id, name);
+            }
+            Expr::CompoundAssign { target, value, .. } => {
+                self.resolve_expression(value);
+                if let Expr::Variable { name, .. } = target.as_ref() {
+                    self.check_assignable(name);
+                }
+                self.resolve_expression(target);
+            }
+            Expr::AssignList { target, value, .. } => {
+                self.resolve_expression(value);
+                self.resolve_target(target);
+            }
+            Expr::Match { subject, arms, .. } => {
+                self.resolve_expression(subject);
+                for arm in arms {
+                    self.resolve_match_arm(arm, |resolver, body| resolver.resolve_expression(body));
+                }
+            }
+        }
+    }
+
+    // like resolving the target as an expression, except variables are written to, so reading
+    // one in its own initializer isn't an error here
+    fn resolve_target(&mut self, target: &Expr) {
+        match target {
+            Expr::Variable { id, name } => {
+                self.check_assignable(name);
+                self.resolve_local(*id, name);
+            }
+            Expr::List { elements } => {
+                for element in elements {
+                    self.resolve_target(element);
+                }
+            }
+            _ => self.resolve_expression(target),
+        }
+    }
+
+    // the names bound by the pattern are visible in the guard and the body of their arm only
+    fn resolve_match_arm<T>(&mut self, arm: &MatchArm<T>, resolve_body: fn(&mut Self, &T)) {
+        self.begin_scope();
+        for name in arm.pattern.bindings() {
+            self.declare(name);
+            self.define(name);
+        }
+        if let Some(guard) = &arm.guard {
+            self.resolve_expression(guard);
+        }
+        resolve_body(self, &arm.body);
+        self.end_scope();
+    }
+
+    fn resolve_function(&mut self, params: &[Param], body: &[Stmt], f_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = f_type;
+
+        self.begin_scope();
+        // defaults run in the function's scope, so they can use the parameters before them
+        for param in params {
+            self.declare(&param.name);
+            if let Some(default) = &param.default {
+                self.resolve_expression(default);
+            }
+            self.define(&param.name);
+        }
+        self.resolve_statements(body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+    }
+
+    fn resolve_local(&mut self, id: ExprId, name: &Token) {
+        let depth = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name.lexeme));
+
+        if let Some(depth) = depth {
+            self.locals.insert(id, depth);
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            // a global declared again with `var` is a variable again
+            self.global_constants.remove(&name.lexeme);
+            return;
+        };
+
+        if scope
+            .insert(name.lexeme.clone(), Variable::Declared)
+            .is_some()
+        {
+            self.error(name, "Already a variable with this name in this scope.");
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), Variable::Defined);
+        }
+    }
+
+    fn define_constant(&mut self, name: &Token) {
+        match self.scopes.last_mut() {
+            Some(scope) => {
+                scope.insert(name.lexeme.clone(), Variable::Constant);
+            }
+            None => {
+                self.global_constants.insert(name.lexeme.clone());
+            }
+        }
+    }
+
+    // the innermost declaration with the name decides, same as resolve_local
+    fn check_assignable(&mut self, name: &Token) {
+        let variable = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme));
+
+        let constant = match variable {
+            Some(variable) => *variable == Variable::Constant,
+            None => self.global_constants.contains(&name.lexeme),
+        };
+
+        if constant {
+            self.error(name, &format!("Cannot assign to constant {}.", name.lexeme));
+        }
+    }
+
+    fn error(&mut self, token: &Token, msg: &str) {
+        self.errors.push(ParseError {
+            token: token.clone(),
+            msg: msg.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parser::Parser, scanner::Scanner};
+
+    use super::*;
+
+    fn resolve(src: &str) -> Result<Locals, Vec<ParseError>> {
+        let mut scanner = Scanner::new(src.to_string());
+        let tokens = scanner
+            .scan_tokens()
+            .expect("There shouldn't be a scan error");
+        let statements: Vec<Stmt> = Parser::new(tokens)
+            .parse()
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .expect("There shouldn't be a parse error");
+
+        Resolver::new().resolve(&statements)
+    }
+
+    fn resolve_err(src: &str) -> String {
+        let errors = resolve(src).expect_err("Should be an error");
+        errors[0].msg.clone()
+    }
+
+    #[test]
+    fn resolves_locals_and_skips_globals() {
+        let locals = resolve(
+            r#"
+            var global = 1;
+            {
+                var a = global;
+                {
+                    print a;
+                }
+            }"#,
+        )
+        .expect("There shouldn't be an error");
+
+        let mut depths: Vec<usize> = locals.values().copied().collect();
+        depths.sort();
+
+        // `global` is never in the table, `a` is read from one scope deeper than it's declared
+        assert_eq!(depths, vec![1]);
+    }
+
+    #[test]
+    fn error_reading_local_in_initializer() {
+        let msg = resolve_err("{ var a = 1; { var a = a; } }");
+
+        assert_eq!(msg, "Can't read local variable in its own initializer.");
+    }
+
+    #[test]
+    fn error_duplicate_declaration() {
+        let msg = resolve_err("{ var a = 1; var a = 2; }");
+
+        assert_eq!(msg, "Already a variable with this name in this scope.");
+    }
+
+    #[test]
+    fn error_top_level_return() {
+        let msg = resolve_err("return 1;");
+
+        assert_eq!(msg, "Can't return from top-level code.");
+    }
+
+    #[test]
+    fn error_this_outside_class() {
+        let msg = resolve_err("fun f() { return this; }");
+
+        assert_eq!(msg, "Can't use 'this' outside of a class.");
+    }
+
+    #[test]
+    fn error_return_value_from_initializer() {
+        let msg = resolve_err("class A { init() { return 1; } }");
+
+        assert_eq!(msg, "Can't return a value from an initializer.");
+    }
+
+    #[test]
+    fn error_inherit_from_itself() {
+        let msg = resolve_err("class A < A {}");
+
+        assert_eq!(msg, "A class can't inherit from itself.");
+    }
+
+    #[test]
+    fn error_super_without_superclass() {
+        let msg = resolve_err("class A { f() { super.f(); } }");
+
+        assert_eq!(msg, "Can't use 'super' in a class with no superclass.");
+    }
+
+    #[test]
+    fn error_super_outside_class() {
+        let msg = resolve_err("super.f();");
+
+        assert_eq!(msg, "Can't use 'super' outside of a class.");
+    }
+
+    #[test]
+    fn error_default_reads_own_param() {
+        let msg = resolve_err("fun f(a = a) {}");
+
+        assert_eq!(msg, "Can't read local variable in its own initializer.");
+    }
+
+    #[test]
+    fn error_yield_outside_generator() {
+        let top_level = resolve_err("yield 1;");
+        let initializer = resolve_err("class A { init() { yield 1; } }");
+
+        assert_eq!(top_level, "Can't yield from top-level code.");
+        assert_eq!(initializer, "Can't yield from an initializer.");
+    }
+
+    #[test]
+    fn error_assign_constant() {
+        let global = resolve_err("const x = 1; x = 2;");
+        let local = resolve_err("{ const x = 1; x += 1; }");
+        let destructured = resolve_err("var a; const b = 1; [a, b] = [1, 2];");
+
+        assert_eq!(global, "Cannot assign to constant x.");
+        assert_eq!(local, "Cannot assign to constant x.");
+        assert_eq!(destructured, "Cannot assign to constant b.");
+    }
+
+    #[test]
+    fn constant_shadowed_by_variable() {
+        resolve("const x = 1; { var x = 1; x = 2; } var x = 3; x = 4;")
+            .expect("There shouldn't be an error");
+    }
+}
@@ -13,6 +13,9 @@ pub struct Scanner {
     start: usize,
     current: usize,
     line: usize,
+    // one entry per unfinished `${`: the count of braces opened inside of it and the line of the
+    // string it belongs to
+    interpolations: Vec<(usize, usize)>,
 }
 
 #[derive(Debug)]
@@ -43,6 +46,7 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            interpolations: Vec::new(),
         }
     }
 
@@ -58,6 +62,13 @@ impl Scanner {
             }
         }
 
+        for (_, line) in self.interpolations.drain(..) {
+            errors.push(RloxError {
+                msg: "Unterminated string interpolation".to_string(),
+                line,
+            });
+        }
+
         self.tokens.push(Token {
             t_type: TokenType::EOF,
             lexeme: "".to_string(),
@@ -84,22 +95,85 @@ impl Scanner {
         match token {
             '(' => self.add_token(TokenType::LeftParen),
             ')' => self.add_token(TokenType::RightParen),
-            '{' => self.add_token(TokenType::LeftBrace),
-            '}' => self.add_token(TokenType::RightBrace),
+            '{' => {
+                if let Some((braces, _)) = self.interpolations.last_mut() {
+                    *braces += 1;
+                }
+                self.add_token(TokenType::LeftBrace)
+            }
+            // the brace closing a `${` continues the string it was opened in
+            '}' if self
+                .interpolations
+                .last()
+                .is_some_and(|(braces, _)| *braces == 0) =>
+            {
+                let (_, start_line) = self.interpolations.pop().unwrap();
+                let empty = self
+                    .tokens
+                    .last()
+                    .is_some_and(|t| t.t_type == TokenType::Interpolation);
+                let line = self.line;
+                error = self.string_segment(start_line);
+                if empty {
+                    error = Err(RloxError {
+                        msg: "Empty string interpolation".to_string(),
+                        line,
+                    });
+                }
+            }
+            '}' => {
+                if let Some((braces, _)) = self.interpolations.last_mut() {
+                    *braces -= 1;
+                }
+                self.add_token(TokenType::RightBrace)
+            }
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
             ',' => self.add_token(TokenType::Comma),
+            '.' if self.peek() == Some('.') && self.peek_double() == Some('.') => {
+                self.advance();
+                self.advance();
+                self.add_token(TokenType::DotDotDot)
+            }
+            // a number only takes a '.' that is followed by a digit, so `1..2` ends up here
+            '.' if self.peek_and_match('.') => {
+                if self.peek_and_match('=') {
+                    self.add_token(TokenType::DotDotEqual)
+                } else {
+                    self.add_token(TokenType::DotDot)
+                }
+            }
             '.' => self.add_token(TokenType::Dot),
+            '-' if self.peek_and_match('-') => self.add_token(TokenType::MinusMinus),
+            '-' if self.peek_and_match('=') => self.add_token(TokenType::MinusEqual),
             '-' => self.add_token(TokenType::Minus),
+            '+' if self.peek_and_match('+') => self.add_token(TokenType::PlusPlus),
+            '+' if self.peek_and_match('=') => self.add_token(TokenType::PlusEqual),
             '+' => self.add_token(TokenType::Plus),
             ';' => self.add_token(TokenType::Semicolon),
+            '*' if self.peek_and_match('*') => self.add_token(TokenType::StarStar),
+            '*' if self.peek_and_match('=') => self.add_token(TokenType::StarEqual),
             '*' => self.add_token(TokenType::Star),
+            '%' => self.add_token(TokenType::Percent),
+            // floor division can't be `//`, that's already a comment
+            '~' if self.peek_and_match('/') => self.add_token(TokenType::TildeSlash),
+            '~' => self.add_token(TokenType::Tilde),
+            '&' => self.add_token(TokenType::Ampersand),
+            '|' => self.add_token(TokenType::Pipe),
+            '^' => self.add_token(TokenType::Caret),
             '!' if self.peek_and_match('=') => self.add_token(TokenType::BangEqual),
             '!' => self.add_token(TokenType::Bang),
             '=' if self.peek_and_match('=') => self.add_token(TokenType::EqualEqual),
+            '=' if self.peek_and_match('>') => self.add_token(TokenType::Arrow),
             '=' => self.add_token(TokenType::Equal),
             '<' if self.peek_and_match('=') => self.add_token(TokenType::LessEqual),
+            '<' if self.peek_and_match('<') => self.add_token(TokenType::LessLess),
             '<' => self.add_token(TokenType::Less),
             '>' if self.peek_and_match('=') => self.add_token(TokenType::GreaterEqual),
+            '>' if self.peek_and_match('>') => self.add_token(TokenType::GreaterGreater),
             '>' => self.add_token(TokenType::Greater),
+            '?' if self.peek_and_match('?') => self.add_token(TokenType::QuestionQuestion),
+            '?' if self.peek_and_match('.') => self.add_token(TokenType::QuestionDot),
             '?' => self.add_token(TokenType::Question),
             ':' => self.add_token(TokenType::Colon),
             // checking for comments and just advance the iterator if it's a comment
@@ -121,12 +195,13 @@ impl Scanner {
                 self.advance();
                 self.advance();
             }
+            '/' if self.peek_and_match('=') => self.add_token(TokenType::SlashEqual),
             '/' => self.add_token(TokenType::Slash),
             '"' => error = self.string(),
             ' ' | '\r' | '\t' => (),
             '\n' => self.line += 1,
 
-            '0'..='9' => self.number(),
+            '0'..='9' => error = self.number(),
             'a'..='z' | 'A'..='Z' | '_' => self.identifier(),
             _ => {
                 error = Err(RloxError {
@@ -175,9 +250,17 @@ impl Scanner {
     }
 
     fn string(&mut self) -> Result<(), RloxError> {
-        let start_line = self.line;
-        while self.peek().is_some_and(|x| x != '"') {
-            if self.peek().is_some_and(|x| x == '\n') {
+        self.string_segment(self.line)
+    }
+
+    // scans up to the closing quote or the next `${`. Both the opening quote and the `}` closing
+    // an interpolation are one character long, so the segment always starts at self.start + 1
+    fn string_segment(&mut self, start_line: usize) -> Result<(), RloxError> {
+        while let Some(c) = self.peek() {
+            if c == '"' || (c == '$' && self.peek_double() == Some('{')) {
+                break;
+            }
+            if c == '\n' {
                 self.line += 1;
             }
             self.advance();
@@ -191,21 +274,34 @@ impl Scanner {
             return Err(error);
         }
 
-        self.advance();
+        if self.peek_and_match('"') {
+            // clean out the quotes and wrap it in a string literal type
+            let value = LiteralType::String(
+                self.source
+                    .slice(self.start + 1..self.current - 1)
+                    .to_string(),
+            );
 
-        // clean out the quotes and wrap it in a string literal type
+            self.add_token_literal(TokenType::String, Some(value));
+            return Ok(());
+        }
+
+        // consume the `${`, the embedded expression is scanned like any other tokens
+        self.advance();
+        self.advance();
         let value = LiteralType::String(
             self.source
-                .slice(self.start + 1..self.current - 1)
+                .slice(self.start + 1..self.current - 2)
                 .to_string(),
         );
 
-        self.add_token_literal(TokenType::String, Some(value));
+        self.add_token_literal(TokenType::Interpolation, Some(value));
+        self.interpolations.push((0, start_line));
 
         Ok(())
     }
 
-    fn number(&mut self) {
+    fn number(&mut self) -> Result<(), RloxError> {
         while matches!(self.peek(), Some('0'..='9')) {
             self.advance();
         }
@@ -216,15 +312,27 @@ impl Scanner {
             while matches!(self.peek(), Some('0'..='9')) {
                 self.advance();
             }
+
+            let number: f64 = self
+                .source
+                .slice(self.start..self.current)
+                .parse()
+                .expect("There shouldn't be any errors. Please check");
+
+            self.add_token_literal(TokenType::Number, Some(LiteralType::Number(number)));
+            return Ok(());
         }
 
-        let number: f64 = self
-            .source
-            .slice(self.start..self.current)
-            .parse()
-            .expect("There shouldn't be any errors. Please check");
+        // only digits were consumed, so the parse can only fail on overflow
+        let Ok(number) = self.source.slice(self.start..self.current).parse::<i64>() else {
+            return Err(RloxError {
+                msg: "Integer literal is too large".to_string(),
+                line: self.line,
+            });
+        };
 
-        self.add_token_literal(TokenType::Number, Some(LiteralType::Number(number)));
+        self.add_token_literal(TokenType::Number, Some(LiteralType::Int(number)));
+        Ok(())
     }
 
     fn identifier(&mut self) {
@@ -245,15 +353,27 @@ fn is_alpha_numeric(chr: char) -> bool {
     matches!(chr ,'0'..='9'| '_' | 'a'..='z'|'A'..='Z')
 }
 
+// whether the text would be scanned as a single identifier token
+pub fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|first| matches!(first, '_' | 'a'..='z' | 'A'..='Z'))
+        && chars.all(is_alpha_numeric)
+        && get_identified_keyword(text).is_none()
+}
+
 fn get_identified_keyword(identifier: &str) -> Option<TokenType> {
     match identifier {
         "and" => Some(TokenType::And),
         "class" => Some(TokenType::Class),
+        "const" => Some(TokenType::Const),
         "else" => Some(TokenType::Else),
         "false" => Some(TokenType::False),
         "for" => Some(TokenType::For),
         "fun" => Some(TokenType::Fun),
         "if" => Some(TokenType::If),
+        "in" => Some(TokenType::In),
         "nil" => Some(TokenType::Nil),
         "or" => Some(TokenType::OR),
         "print" => Some(TokenType::Print),
@@ -263,7 +383,16 @@ fn get_identified_keyword(identifier: &str) -> Option<TokenType> {
         "true" => Some(TokenType::True),
         "var" => Some(TokenType::Var),
         "while" => Some(TokenType::While),
+        "yield" => Some(TokenType::Yield),
         "break" => Some(TokenType::Break),
+        "continue" => Some(TokenType::Continue),
+        "throw" => Some(TokenType::Throw),
+        "try" => Some(TokenType::Try),
+        "catch" => Some(TokenType::Catch),
+        "finally" => Some(TokenType::Finally),
+        "import" => Some(TokenType::Import),
+        "from" => Some(TokenType::From),
+        "match" => Some(TokenType::Match),
         _ => None,
     }
 }
@@ -292,7 +421,88 @@ mod tests {
 
         let expected_tokens = vec![
             LeftParen, LeftParen, RightParen, RightParen, LeftBrace, RightBrace, Bang, Star, Plus,
-            Minus, Slash, Equal, Less, Greater, LessEqual, EqualEqual, EOF,
+            Minus, SlashEqual, Less, Greater, LessEqual, EqualEqual, EOF,
+        ];
+
+        let actual_tokens: Vec<TokenType> = scanner
+            .scan_tokens()
+            .unwrap()
+            .iter()
+            .map(|x| x.t_type)
+            .collect();
+
+        assert!(do_cols_match(&actual_tokens, &expected_tokens));
+    }
+
+    #[test]
+    fn arithmetic_operators_scan() {
+        // `~//` is floor division followed by a slash, a `//` right after `~` doesn't start a
+        // comment
+        let value = "a ** b % c ~/ d // a line comment, floor division is ~/\ne ~// f";
+
+        let mut scanner = Scanner::new(value.to_string());
+
+        let expected_tokens = vec![
+            Identifier, StarStar, Identifier, Percent, Identifier, TildeSlash, Identifier,
+            Identifier, TildeSlash, Slash, Identifier, EOF,
+        ];
+
+        let actual_tokens: Vec<TokenType> = scanner
+            .scan_tokens()
+            .unwrap()
+            .iter()
+            .map(|x| x.t_type)
+            .collect();
+
+        assert!(do_cols_match(&actual_tokens, &expected_tokens));
+    }
+
+    #[test]
+    fn bitwise_operators_scan() {
+        let value = "a & b | c ^ ~d << e >> f <= g ~/ h";
+
+        let mut scanner = Scanner::new(value.to_string());
+
+        let expected_tokens = vec![
+            Identifier,
+            Ampersand,
+            Identifier,
+            Pipe,
+            Identifier,
+            Caret,
+            Tilde,
+            Identifier,
+            LessLess,
+            Identifier,
+            GreaterGreater,
+            Identifier,
+            LessEqual,
+            Identifier,
+            TildeSlash,
+            Identifier,
+            EOF,
+        ];
+
+        let actual_tokens: Vec<TokenType> = scanner
+            .scan_tokens()
+            .unwrap()
+            .iter()
+            .map(|x| x.t_type)
+            .collect();
+
+        assert!(do_cols_match(&actual_tokens, &expected_tokens));
+    }
+
+    #[test]
+    fn assignment_operators_scan() {
+        let value = "a += b -= c *= d /= e++ --f - -g";
+
+        let mut scanner = Scanner::new(value.to_string());
+
+        let expected_tokens = vec![
+            Identifier, PlusEqual, Identifier, MinusEqual, Identifier, StarEqual, Identifier,
+            SlashEqual, Identifier, PlusPlus, MinusMinus, Identifier, Minus, Minus, Identifier,
+            EOF,
         ];
 
         let actual_tokens: Vec<TokenType> = scanner
@@ -357,6 +567,84 @@ mod tests {
         assert_eq!(expected_error, actual_error.clone());
     }
 
+    #[test]
+    fn correct_interpolation_scan() {
+        let value = r#""a ${ {"k": "b ${c}"}["k"] } d""#;
+
+        let mut scanner = Scanner::new(value.to_string());
+
+        let expected_tokens = vec![
+            Interpolation,
+            LeftBrace,
+            TokenType::String,
+            Colon,
+            Interpolation,
+            Identifier,
+            TokenType::String,
+            RightBrace,
+            LeftBracket,
+            TokenType::String,
+            RightBracket,
+            TokenType::String,
+            EOF,
+        ];
+
+        let tokens = scanner.scan_tokens().expect("There shouldn't be an error");
+        let actual_tokens: Vec<TokenType> = tokens.iter().map(|x| x.t_type).collect();
+
+        assert!(do_cols_match(&actual_tokens, &expected_tokens));
+
+        let segments: Vec<std::string::String> = tokens
+            .iter()
+            .filter(|t| matches!(t.t_type, Interpolation))
+            .map(|t| t.literal.as_ref().unwrap().to_string())
+            .collect();
+        assert_eq!(segments, vec!["a ", "b "]);
+        assert_eq!(
+            tokens[tokens.len() - 2]
+                .literal
+                .as_ref()
+                .unwrap()
+                .to_string(),
+            " d"
+        );
+    }
+
+    #[test]
+    fn error_unterminated_interpolation_scan() {
+        let value = r#"
+            "fine"
+            "broken ${ 1 + 2
+            "#
+        .to_string();
+
+        let mut scanner = Scanner::new(value);
+
+        let errors = scanner.scan_tokens().expect_err("Should be an error");
+
+        assert!(errors.errors.contains(&RloxError {
+            msg: "Unterminated string interpolation".to_string(),
+            line: 3,
+        }));
+    }
+
+    #[test]
+    fn error_empty_interpolation_scan() {
+        let value = "\n\"nothing ${} here\"".to_string();
+
+        let mut scanner = Scanner::new(value);
+
+        let errors = scanner.scan_tokens().expect_err("Should be an error");
+
+        assert_eq!(
+            errors.errors,
+            vec![RloxError {
+                msg: "Empty string interpolation".to_string(),
+                line: 2,
+            }]
+        );
+    }
+
     #[test]
     fn correct_whole_number_scan() {
         let value = r#"
@@ -408,4 +696,88 @@ mod tests {
             &actual_value.as_ref().unwrap().clone()
         ))
     }
+
+    #[test]
+    fn int_and_float_literals() {
+        let mut scanner = Scanner::new("123 123.5".to_string());
+
+        let tokens = scanner.scan_tokens().expect("There shouldn't be an error");
+
+        assert!(matches!(
+            tokens[0].literal.as_deref(),
+            Some(LiteralType::Int(123))
+        ));
+        assert!(
+            matches!(tokens[1].literal.as_deref(), Some(LiteralType::Number(n)) if *n == 123.5)
+        );
+    }
+
+    #[test]
+    fn error_int_literal_too_large() {
+        let mut scanner = Scanner::new("99999999999999999999".to_string());
+
+        let errors = scanner.scan_tokens().expect_err("Should be an error");
+
+        assert_eq!(
+            errors.errors,
+            vec![RloxError {
+                msg: "Integer literal is too large".to_string(),
+                line: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn ranges_are_not_fractions() {
+        let mut scanner = Scanner::new("1..2 1..=2 1.5..2".to_string());
+
+        let tokens = scanner.scan_tokens().expect("There shouldn't be an error");
+        let types: Vec<TokenType> = tokens.iter().map(|token| token.t_type).collect();
+
+        use TokenType::*;
+        assert_eq!(
+            types,
+            vec![
+                Number,
+                DotDot,
+                Number,
+                Number,
+                DotDotEqual,
+                Number,
+                Number,
+                DotDot,
+                Number,
+                EOF
+            ]
+        );
+        assert!(matches!(
+            tokens[0].literal.as_deref(),
+            Some(LiteralType::Int(1))
+        ));
+    }
+
+    #[test]
+    fn question_marks() {
+        let mut scanner = Scanner::new("a ? b : c ?? d?.e".to_string());
+
+        let tokens = scanner.scan_tokens().expect("There shouldn't be an error");
+        let types: Vec<TokenType> = tokens.iter().map(|token| token.t_type).collect();
+
+        use TokenType::*;
+        assert_eq!(
+            types,
+            vec![
+                Identifier,
+                Question,
+                Identifier,
+                Colon,
+                Identifier,
+                QuestionQuestion,
+                Identifier,
+                QuestionDot,
+                Identifier,
+                EOF
+            ]
+        );
+    }
 }
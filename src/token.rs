@@ -1,6 +1,13 @@
-use std::fmt::Display;
+use std::{cell::RefCell, fmt::Display, rc::Rc};
 
-use crate::callable::Callable;
+use crate::{
+    callable::Callable,
+    class::{Class, Instance},
+    generator::Generator,
+    map::Map,
+    module::Module,
+    range::Range,
+};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 #[allow(clippy::upper_case_acronyms)]
@@ -9,13 +16,31 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
+    DotDot,
+    DotDotEqual,
+    DotDotDot,
     Minus,
     Plus,
+    MinusMinus,
+    PlusPlus,
+    MinusEqual,
+    PlusEqual,
     Semicolon,
     Slash,
     Star,
+    StarStar,
+    SlashEqual,
+    StarEqual,
+    Percent,
+    TildeSlash,
+    Ampersand,
+    Pipe,
+    Caret,
+    Tilde,
 
     Bang,
     BangEqual,
@@ -25,30 +50,48 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    GreaterGreater,
     Question,
+    QuestionQuestion,
+    QuestionDot,
     Colon,
+    Arrow,
 
     Identifier,
     String,
+    // a string segment that is followed by an embedded `${expression}`
+    Interpolation,
     Number,
 
     And,
     Break,
+    Catch,
     Class,
+    Const,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
+    From,
     If,
+    Import,
+    In,
+    Match,
     Nil,
     OR,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
+    Yield,
 
     EOF,
 }
@@ -57,10 +100,23 @@ pub enum TokenType {
 #[derive(Debug, Clone)]
 pub enum LiteralType {
     String(String),
+    // literals without a decimal point, arithmetic between two of them stays integral
+    Int(i64),
     Number(f64),
     Bool(bool),
     Nil,
     Callable(Callable),
+    Class(Rc<Class>),
+    Instance(Rc<RefCell<Instance>>),
+    // lists are shared by reference, so mutations are visible through every alias
+    List(Rc<RefCell<Vec<LiteralType>>>),
+    Map(Rc<RefCell<Map>>),
+    // the namespace created by `import "mod";`
+    Module(Rc<Module>),
+    // what calling a function that contains `yield` returns
+    Generator(Rc<RefCell<Generator>>),
+    // `a..b` and `a..=b`, copied around like numbers
+    Range(Range),
 }
 
 impl LiteralType {
@@ -77,14 +133,55 @@ impl Display for LiteralType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             LiteralType::String(v) => write!(f, "{v}"),
+            LiteralType::Int(v) => write!(f, "{v}"),
             LiteralType::Number(v) => write!(f, "{v:.2}"),
             LiteralType::Bool(v) => write!(f, "{v}"),
             LiteralType::Nil => write!(f, "nil"),
             LiteralType::Callable(c) => write!(f, "<fn {c}>"),
+            LiteralType::Class(c) => write!(f, "{c}"),
+            LiteralType::Instance(i) => write!(f, "{}", i.borrow()),
+            LiteralType::List(l) => print_once(Rc::as_ptr(l).cast(), f, "[...]", |f| {
+                write!(f, "[")?;
+                for (i, value) in l.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }),
+            LiteralType::Map(m) => print_once(Rc::as_ptr(m).cast(), f, "{...}", |f| {
+                write!(f, "{}", m.borrow())
+            }),
+            LiteralType::Module(m) => write!(f, "{m}"),
+            LiteralType::Generator(g) => write!(f, "{}", g.borrow()),
+            LiteralType::Range(r) => write!(f, "{r}"),
         }
     }
 }
 
+thread_local! {
+    // the containers that are being printed right now. One that shows up inside itself is
+    // printed as a placeholder, otherwise printing it would never end
+    static PRINTING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+fn print_once(
+    container: *const (),
+    f: &mut std::fmt::Formatter<'_>,
+    placeholder: &str,
+    print: impl FnOnce(&mut std::fmt::Formatter<'_>) -> std::fmt::Result,
+) -> std::fmt::Result {
+    if PRINTING.with_borrow(|printing| printing.contains(&container)) {
+        return write!(f, "{placeholder}");
+    }
+
+    PRINTING.with_borrow_mut(|printing| printing.push(container));
+    let result = print(f);
+    PRINTING.with_borrow_mut(|printing| printing.pop());
+    result
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub t_type: TokenType,